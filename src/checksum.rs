@@ -0,0 +1,208 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Algorithm {
+    Crc16Xmodem,
+    Crc16Modbus,
+    Crc8,
+    Crc32,
+    Sum8,
+    Sum16,
+    Xor,
+    Lrc,
+}
+impl Algorithm {
+    pub const ALL: [Algorithm; 8] = [
+        Algorithm::Crc16Xmodem,
+        Algorithm::Crc16Modbus,
+        Algorithm::Crc8,
+        Algorithm::Crc32,
+        Algorithm::Sum8,
+        Algorithm::Sum16,
+        Algorithm::Xor,
+        Algorithm::Lrc,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Crc16Xmodem => "CRC16 XMODEM",
+            Algorithm::Crc16Modbus => "CRC16 MODBUS",
+            Algorithm::Crc8 => "CRC8",
+            Algorithm::Crc32 => "CRC32",
+            Algorithm::Sum8 => "SUM8",
+            Algorithm::Sum16 => "SUM16",
+            Algorithm::Xor => "XOR",
+            Algorithm::Lrc => "LRC",
+        }
+    }
+    /// 校验值默认的字节数
+    pub fn width(&self) -> usize {
+        match self {
+            Algorithm::Crc16Xmodem | Algorithm::Crc16Modbus | Algorithm::Sum16 => 2,
+            Algorithm::Crc32 => 4,
+            Algorithm::Crc8 | Algorithm::Sum8 | Algorithm::Xor | Algorithm::Lrc => 1,
+        }
+    }
+    pub fn calculate(&self, data: &[u8]) -> u32 {
+        match self {
+            Algorithm::Crc16Xmodem => crc16::State::<crc16::XMODEM>::calculate(data) as u32,
+            Algorithm::Crc16Modbus => crc16::State::<crc16::MODBUS>::calculate(data) as u32,
+            Algorithm::Crc8 => crc8(data) as u32,
+            Algorithm::Crc32 => crc32(data),
+            Algorithm::Sum8 => data.iter().fold(0u8, |acc, &x| acc.wrapping_add(x)) as u32,
+            Algorithm::Sum16 => data.iter().fold(0u16, |acc, &x| acc.wrapping_add(x as u16)) as u32,
+            Algorithm::Xor => data.iter().fold(0u8, |acc, &x| acc ^ x) as u32,
+            Algorithm::Lrc => data
+                .iter()
+                .fold(0u8, |acc, &x| acc.wrapping_add(x))
+                .wrapping_neg() as u32,
+        }
+    }
+}
+
+/// CRC-8，多项式 0x07，初值 0
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &x| {
+        crc ^= x;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// CRC-32 (IEEE 802.3)
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |mut crc, &x| {
+        crc ^= x as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FrameStatus {
+    Unchecked,
+    Ok,
+    Bad,
+}
+
+/// 校验值位于 `frame[len - trailer - width..len - trailer]`，
+/// 参与计算的数据为 `frame[start..len - trailer - width]`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ChecksumSpec {
+    pub algorithm: Algorithm,
+    /// 校验值字节数，1/2/4，计算结果按这个宽度截断
+    pub width: usize,
+    pub start: usize,
+    /// 校验值之后还有多少字节，比如帧尾
+    pub trailer: usize,
+    pub big_endian: bool,
+}
+impl ChecksumSpec {
+    pub fn encode(&self, value: u32) -> Vec<u8> {
+        let width = self.width;
+        if self.big_endian {
+            value.to_be_bytes()[4 - width..].to_vec()
+        } else {
+            value.to_le_bytes()[..width].to_vec()
+        }
+    }
+    pub fn verify(&self, frame: &[u8]) -> FrameStatus {
+        let width = self.width;
+        let Some(end) = frame.len().checked_sub(self.trailer + width) else {
+            return FrameStatus::Bad;
+        };
+        if self.start > end {
+            return FrameStatus::Bad;
+        }
+        let value = self.algorithm.calculate(&frame[self.start..end]);
+        if self.encode(value) == frame[end..end + width] {
+            FrameStatus::Ok
+        } else {
+            FrameStatus::Bad
+        }
+    }
+    /// 在倒数第 `trailer` 个字节前插入 `bytes[start..len - trailer]` 的校验值
+    pub fn append(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+        let end = bytes.len().checked_sub(self.trailer)?;
+        if self.start > end {
+            return None;
+        }
+        let value = self.algorithm.calculate(&bytes[self.start..end]);
+        let mut frame = bytes[..end].to_vec();
+        frame.extend(self.encode(value));
+        frame.extend_from_slice(&bytes[end..]);
+        Some(frame)
+    }
+}
+
+#[test]
+fn test_algorithms() {
+    let data = b"123456789";
+    let expected: [u32; 8] = [0x31C3, 0x4B37, 0xF4, 0xCBF4_3926, 0xDD, 0x01DD, 0x31, 0x23];
+    for (algorithm, expected) in Algorithm::ALL.iter().zip(expected) {
+        assert_eq!(algorithm.calculate(data), expected, "{}", algorithm.name());
+    }
+}
+
+#[test]
+fn test_verify() {
+    let spec = ChecksumSpec {
+        algorithm: Algorithm::Crc16Modbus,
+        width: 2,
+        start: 0,
+        trailer: 0,
+        big_endian: false,
+    };
+    // Modbus 读保持寄存器请求
+    let frame = [0x01, 0x03, 0x00, 0x00, 0x00, 0x0a, 0xc5, 0xcd];
+    assert_eq!(spec.verify(&frame), FrameStatus::Ok);
+    assert_eq!(spec.verify(&frame[..7]), FrameStatus::Bad);
+    assert_eq!(spec.verify(&frame[..1]), FrameStatus::Bad);
+
+    let spec = ChecksumSpec {
+        algorithm: Algorithm::Sum8,
+        width: 1,
+        start: 1,
+        trailer: 1,
+        big_endian: true,
+    };
+    assert_eq!(
+        spec.verify(&[0x68, 0x01, 0x02, 0x03, 0x16]),
+        FrameStatus::Ok
+    );
+}
+
+#[test]
+fn test_append() {
+    let spec = ChecksumSpec {
+        algorithm: Algorithm::Crc16Modbus,
+        width: 2,
+        start: 0,
+        trailer: 0,
+        big_endian: false,
+    };
+    let frame = spec.append(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0a]).unwrap();
+    assert_eq!(frame, [0x01, 0x03, 0x00, 0x00, 0x00, 0x0a, 0xc5, 0xcd]);
+    assert_eq!(spec.verify(&frame), FrameStatus::Ok);
+
+    // SUM16 截断为 1 字节，插在帧尾 0x16 之前
+    let spec = ChecksumSpec {
+        algorithm: Algorithm::Sum16,
+        width: 1,
+        start: 1,
+        trailer: 1,
+        big_endian: true,
+    };
+    let frame = spec.append(&[0x68, 0xff, 0x02, 0x16]).unwrap();
+    assert_eq!(frame, [0x68, 0xff, 0x02, 0x01, 0x16]);
+    assert!(spec.append(&[0x68]).is_none());
+}
@@ -0,0 +1,430 @@
+use memchr::memmem;
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::time::Instant;
+use tokio_util::{bytes::BytesMut, codec::Decoder};
+
+use crate::record::Timestamp;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DelimiterPosition {
+    Header,
+    Trailer,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Framing {
+    /// 每次读到多少就是一帧
+    Raw,
+    Delimiter {
+        delimiter: Vec<u8>,
+        position: DelimiterPosition,
+    },
+    Fixed(usize),
+    /// 超过空闲时间没有新数据，缓冲区里的数据算一帧
+    Idle(Duration),
+    Length(LengthField),
+}
+
+/// 帧头 + 长度字段分帧的参数
+#[derive(Clone, PartialEq, Debug)]
+pub struct LengthField {
+    pub header: Vec<u8>,
+    /// 长度字段相对帧起始的偏移
+    pub offset: usize,
+    /// 长度字段字节数，1/2/4
+    pub width: usize,
+    pub big_endian: bool,
+    /// 整帧长度 = 长度字段的值 + adjustment
+    pub adjustment: isize,
+}
+impl LengthField {
+    fn value(&self, field: &[u8]) -> usize {
+        let mut buf = [0u8; 8];
+        if self.big_endian {
+            buf[8 - field.len()..].copy_from_slice(field);
+            u64::from_be_bytes(buf) as usize
+        } else {
+            buf[..field.len()].copy_from_slice(field);
+            u64::from_le_bytes(buf) as usize
+        }
+    }
+}
+
+/// 单帧的最大长度，长度字段解析出的帧长超过这个值就认为帧头是误判；
+/// 其它分帧方式缓冲的数据超过这个值还分不出帧时，直接输出一帧，避免对不上格式时缓冲区无限增长
+const MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// 所有流式通讯（串口、TCP）共用的分帧解码器
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameCodec {
+    framing: Framing,
+}
+impl FrameCodec {
+    pub fn new(framing: Framing) -> FrameCodec {
+        FrameCodec { framing }
+    }
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        match self.framing {
+            Framing::Idle(timeout) => Some(timeout),
+            _ => None,
+        }
+    }
+    /// 按分帧方式切出一帧，数据不够时返回 `None`
+    fn split_frame(&self, src: &mut BytesMut) -> Option<BytesMut> {
+        match &self.framing {
+            Framing::Raw => Some(src.split()),
+            Framing::Delimiter {
+                delimiter,
+                position: DelimiterPosition::Header,
+            } => {
+                if let Some(frame) = split_before_header(src, delimiter) {
+                    return frame;
+                }
+                // 下一个帧头出现时，当前帧才算完整
+                memmem::find(&src[delimiter.len()..], delimiter)
+                    .map(|index| src.split_to(index + delimiter.len()))
+            }
+            Framing::Delimiter {
+                delimiter,
+                position: DelimiterPosition::Trailer,
+            } => memmem::find(src, delimiter).map(|index| src.split_to(index + delimiter.len())),
+            Framing::Fixed(len) => {
+                if src.len() >= *len {
+                    Some(src.split_to(*len))
+                } else {
+                    None
+                }
+            }
+            Framing::Idle(_) => None,
+            Framing::Length(field) => {
+                if let Some(frame) = split_before_header(src, &field.header) {
+                    return frame;
+                }
+                let field_end = field.offset + field.width;
+                let len = src.get(field.offset..field_end)?;
+                let frame_len = field.value(len) as isize + field.adjustment;
+                if frame_len < field_end.max(field.header.len()) as isize
+                    || frame_len > MAX_FRAME_LEN as isize
+                {
+                    // 载荷里恰好出现了帧头，丢到下一个帧头为止重新同步
+                    let next = memmem::find(&src[1..], &field.header)
+                        .map(|index| index + 1)
+                        .unwrap_or(src.len());
+                    return Some(src.split_to(next));
+                }
+                let frame_len = frame_len as usize;
+                if src.len() >= frame_len {
+                    Some(src.split_to(frame_len))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// 帧头之前的数据单独成一帧，避免丢数据
+fn split_before_header(src: &mut BytesMut, header: &[u8]) -> Option<Option<BytesMut>> {
+    match memmem::find(src, header) {
+        Some(0) => None,
+        Some(index) => Some(Some(src.split_to(index))),
+        None => Some(None),
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.is_empty() {
+            return Ok(None);
+        }
+        let frame = self.split_frame(src);
+        // 对不上分帧格式时不再等待，缓冲的数据按最大长度输出
+        if frame.is_none() && src.len() >= MAX_FRAME_LEN {
+            return Ok(Some(src.split_to(MAX_FRAME_LEN)));
+        }
+        Ok(frame)
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(buf)? {
+            Some(frame) => Ok(Some(frame)),
+            None => {
+                if buf.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(buf.split()))
+                }
+            }
+        }
+    }
+}
+
+/// 按分帧方式读取流式数据，空闲分帧需要最后一次收到数据的时间，所以不用 `FramedRead`
+pub struct FrameReader<R> {
+    inner: R,
+    codec: FrameCodec,
+    buf: BytesMut,
+    last_read: Instant,
+    /// 缓冲区中每次读到的数据的起始位置和到达时间，帧的时间取第一个字节所在的那次读取
+    reads: VecDeque<(usize, Timestamp)>,
+    eof: bool,
+}
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    pub fn new(inner: R, codec: FrameCodec) -> FrameReader<R> {
+        FrameReader {
+            inner,
+            codec,
+            buf: BytesMut::new(),
+            last_read: Instant::now(),
+            reads: VecDeque::new(),
+            eof: false,
+        }
+    }
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+    /// 读取下一帧和它第一个字节的到达时间，结束后返回 `None`
+    ///
+    /// 可以在 `select!` 中被取消，已读到的数据留在缓冲区，空闲时间从最后一次收到数据算起
+    pub async fn next(&mut self) -> Option<io::Result<(BytesMut, Timestamp)>> {
+        loop {
+            if self.eof {
+                let frame = self.codec.decode_eof(&mut self.buf).transpose()?;
+                return Some(frame.map(|frame| self.stamp(frame)));
+            }
+            match self.codec.decode(&mut self.buf) {
+                Ok(Some(frame)) => return Some(Ok(self.stamp(frame))),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            let deadline = match self.codec.idle_timeout() {
+                Some(idle) if !self.buf.is_empty() => Some(self.last_read + idle),
+                _ => None,
+            };
+            self.buf.reserve(4096);
+            let start = self.buf.len();
+            tokio::select! {
+                res = self.inner.read_buf(&mut self.buf) => match res {
+                    Ok(0) => self.eof = true,
+                    Ok(_) => {
+                        self.last_read = Instant::now();
+                        self.reads.push_back((start, Timestamp::now()));
+                    }
+                    Err(e) => return Some(Err(e)),
+                },
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)),
+                    if deadline.is_some() => {
+                    let frame = self.buf.split();
+                    return Some(Ok(self.stamp(frame)));
+                }
+            }
+        }
+    }
+    /// 从缓冲区头部切走了 `frame`，取它的到达时间并把剩下的位置前移
+    fn stamp(&mut self, frame: BytesMut) -> (BytesMut, Timestamp) {
+        let len = frame.len();
+        let stamp = self
+            .reads
+            .front()
+            .map_or_else(Timestamp::now, |&(_, stamp)| stamp);
+        while self.reads.get(1).is_some_and(|&(start, _)| start <= len) {
+            self.reads.pop_front();
+        }
+        if self.buf.is_empty() {
+            self.reads.clear();
+        }
+        for (start, _) in self.reads.iter_mut() {
+            *start = start.saturating_sub(len);
+        }
+        (frame, stamp)
+    }
+}
+
+#[test]
+fn test_header_codec() {
+    let mut codec = FrameCodec::new(Framing::Delimiter {
+        delimiter: vec![0x55, 0xaa],
+        position: DelimiterPosition::Header,
+    });
+    let mut buf = BytesMut::from(&[0x01, 0x02, 0x55, 0xaa, 0x03, 0x55][..]);
+    // 帧头前的杂散数据
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap()[..], [0x01, 0x02]);
+    // 没有遇到下一个帧头前不出帧
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    buf.extend_from_slice(&[0xaa, 0x04]);
+    assert_eq!(
+        codec.decode(&mut buf).unwrap().unwrap()[..],
+        [0x55, 0xaa, 0x03]
+    );
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    assert_eq!(
+        codec.decode_eof(&mut buf).unwrap().unwrap()[..],
+        [0x55, 0xaa, 0x04]
+    );
+    assert!(codec.decode_eof(&mut buf).unwrap().is_none());
+}
+
+#[test]
+fn test_trailer_and_fixed_codec() {
+    let mut codec = FrameCodec::new(Framing::Delimiter {
+        delimiter: b"\r\n".to_vec(),
+        position: DelimiterPosition::Trailer,
+    });
+    let mut buf = BytesMut::from(&b"OK\r\nAT"[..]);
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap()[..], b"OK\r\n"[..]);
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+
+    let mut codec = FrameCodec::new(Framing::Fixed(3));
+    let mut buf = BytesMut::from(&[1, 2, 3, 4, 5][..]);
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap()[..], [1, 2, 3]);
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+}
+
+#[test]
+fn test_length_codec() {
+    // 长度为 16 位小端，包含帧头和 2 字节校验
+    let mut codec = FrameCodec::new(Framing::Length(LengthField {
+        header: vec![0x55, 0xaa],
+        offset: 2,
+        width: 2,
+        big_endian: false,
+        adjustment: 0,
+    }));
+    let frame = [0x55, 0xaa, 0x08, 0x00, 0x55, 0xaa, 0x12, 0x34];
+    let mut buf = BytesMut::from(&frame[..]);
+    buf.extend_from_slice(&frame[..5]);
+    // 载荷里出现帧头也不会被截断
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap()[..], frame);
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    buf.extend_from_slice(&frame[5..]);
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap()[..], frame);
+
+    // 长度为 8 位，只计算长度字段之后的载荷，不含 2 字节校验
+    let mut codec = FrameCodec::new(Framing::Length(LengthField {
+        header: vec![0x55, 0xaa],
+        offset: 2,
+        width: 1,
+        big_endian: true,
+        adjustment: 3 + 2,
+    }));
+    let frame = [0x55, 0xaa, 0x01, 0xff, 0x12, 0x34];
+    let mut buf = BytesMut::from(&frame[..]);
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap()[..], frame);
+}
+
+#[test]
+fn test_length_codec_resync() {
+    let mut codec = FrameCodec::new(Framing::Length(LengthField {
+        header: vec![0x55, 0xaa],
+        offset: 2,
+        width: 2,
+        big_endian: true,
+        adjustment: 0,
+    }));
+    // 帧长比长度字段还短，说明帧头是误判
+    let mut buf = BytesMut::from(&[0x55, 0xaa, 0x00, 0x01, 0x55, 0xaa, 0x00, 0x04][..]);
+    assert_eq!(
+        codec.decode(&mut buf).unwrap().unwrap()[..],
+        [0x55, 0xaa, 0x00, 0x01]
+    );
+    assert_eq!(
+        codec.decode(&mut buf).unwrap().unwrap()[..],
+        [0x55, 0xaa, 0x00, 0x04]
+    );
+}
+
+#[test]
+fn test_frame_len_limit() {
+    // 设备不发帧头，也不能一直缓冲下去
+    let mut codec = FrameCodec::new(Framing::Delimiter {
+        delimiter: vec![0x55, 0xaa],
+        position: DelimiterPosition::Header,
+    });
+    let mut buf = BytesMut::from(&vec![0x01; MAX_FRAME_LEN - 1][..]);
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    buf.extend_from_slice(&[0x02, 0x03]);
+    assert_eq!(
+        codec.decode(&mut buf).unwrap().unwrap().len(),
+        MAX_FRAME_LEN
+    );
+    assert_eq!(buf[..], [0x03]);
+
+    let mut codec = FrameCodec::new(Framing::Idle(Duration::from_millis(10)));
+    let mut buf = BytesMut::from(&vec![0x01; MAX_FRAME_LEN][..]);
+    assert_eq!(
+        codec.decode(&mut buf).unwrap().unwrap().len(),
+        MAX_FRAME_LEN
+    );
+    assert!(buf.is_empty());
+}
+
+#[tokio::test]
+async fn test_idle_frame_reader() {
+    use tokio::io::AsyncWriteExt;
+
+    let (mut peer, stream) = tokio::io::duplex(64);
+    let codec = FrameCodec::new(Framing::Idle(Duration::from_millis(50)));
+    let mut reader = FrameReader::new(stream, codec);
+    let read = tokio::spawn(async move {
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next().await {
+            frames.push(frame.unwrap().0.to_vec());
+        }
+        frames
+    });
+    // 同一段连续数据中的间隔小于空闲时间，不会被拆开
+    for chunk in [&b"hel"[..], b"lo"] {
+        peer.write_all(chunk).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    peer.write_all(b"world").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    peer.write_all(b"!").await.unwrap();
+    drop(peer);
+    let frames = read.await.unwrap();
+    assert_eq!(
+        frames,
+        vec![b"hello".to_vec(), b"world".to_vec(), b"!".to_vec()]
+    );
+}
+
+#[tokio::test]
+async fn test_frame_reader_timestamp() {
+    use crate::record::monotonic_micros;
+    use tokio::io::AsyncWriteExt;
+
+    let (mut peer, stream) = tokio::io::duplex(64);
+    let codec = FrameCodec::new(Framing::Delimiter {
+        delimiter: vec![0x55, 0xaa],
+        position: DelimiterPosition::Header,
+    });
+    let mut reader = FrameReader::new(stream, codec);
+    let read = tokio::spawn(async move {
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next().await {
+            frames.push(frame.unwrap());
+        }
+        frames
+    });
+    let first = monotonic_micros();
+    peer.write_all(&[0x55, 0xaa, 0x01]).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let second = monotonic_micros();
+    peer.write_all(&[0x02, 0x55, 0xaa, 0x03]).await.unwrap();
+    drop(peer);
+    let frames = read.await.unwrap();
+    assert_eq!(frames.len(), 2);
+    // 帧在下一个帧头到达时才输出，时间仍然是第一个字节到达的时间
+    assert_eq!(frames[0].0[..], [0x55, 0xaa, 0x01, 0x02]);
+    assert!(frames[0].1.mono >= first && frames[0].1.mono < second);
+    assert_eq!(frames[1].0[..], [0x55, 0xaa, 0x03]);
+    assert!(frames[1].1.mono >= second);
+}
@@ -0,0 +1,207 @@
+use std::ops::Range;
+
+use chrono::{DateTime, Local};
+
+use crate::encoding::TextEncoding;
+use crate::record::Record;
+
+/// 数据区的显示方式，只影响显示，历史记录保存的始终是原始字节
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DisplayMode {
+    Hex,
+    /// 按选择的编码解码，控制字符和无法解码的字节转义，比如 `\r` `\n` `\x00`
+    Text,
+    /// 同一行里 HEX 和 ASCII 并排，不可打印的字符显示为 `.`
+    HexDump,
+    Decimal,
+}
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 4] = [
+        DisplayMode::Hex,
+        DisplayMode::Text,
+        DisplayMode::HexDump,
+        DisplayMode::Decimal,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisplayMode::Hex => "HEX",
+            DisplayMode::Text => "文本",
+            DisplayMode::HexDump => "HEX+ASCII",
+            DisplayMode::Decimal => "十进制",
+        }
+    }
+    pub fn format(&self, bytes: &[u8], encoding: TextEncoding) -> String {
+        self.highlight(bytes, encoding, &[])
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect()
+    }
+    /// 同 `format`，`ranges` 中的字节标记为高亮，返回依次排列的片段和是否高亮
+    pub fn highlight(
+        &self,
+        bytes: &[u8],
+        encoding: TextEncoding,
+        ranges: &[Range<usize>],
+    ) -> Vec<(String, bool)> {
+        let marked = |i: usize| ranges.iter().any(|range| range.contains(&i));
+        let mut segments: Vec<(String, bool)> = Vec::new();
+        let mut push = |text: &str, highlight: bool| match segments.last_mut() {
+            Some((last, h)) if *h == highlight => last.push_str(text),
+            _ => segments.push((text.to_string(), highlight)),
+        };
+        match self {
+            DisplayMode::Hex | DisplayMode::HexDump | DisplayMode::Decimal => {
+                for (i, b) in bytes.iter().enumerate() {
+                    if i > 0 {
+                        push(" ", marked(i - 1) && marked(i));
+                    }
+                    match self {
+                        DisplayMode::Decimal => push(&b.to_string(), marked(i)),
+                        _ => push(&format!("{:02X}", b), marked(i)),
+                    }
+                }
+                if *self == DisplayMode::HexDump {
+                    push("  |", false);
+                    for (i, &b) in bytes.iter().enumerate() {
+                        let c = match b {
+                            0x20..=0x7e => b as char,
+                            _ => '.',
+                        };
+                        push(c.encode_utf8(&mut [0; 4]), marked(i));
+                    }
+                    push("|", false);
+                }
+            }
+            DisplayMode::Text => {
+                // 在高亮边界处分段解码，边界落在多字节字符中间时两边按无法解码的字节显示
+                let mut bounds = vec![0, bytes.len()];
+                for range in ranges {
+                    bounds.extend([range.start.min(bytes.len()), range.end.min(bytes.len())]);
+                }
+                bounds.sort_unstable();
+                bounds.dedup();
+                for pair in bounds.windows(2) {
+                    push(&encoding.escape(&bytes[pair[0]..pair[1]]), marked(pair[0]));
+                }
+            }
+        }
+        segments
+    }
+}
+
+/// 每行开头的时间显示方式
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TimeFormat {
+    /// 本地时间，精确到毫秒
+    Wall,
+    /// 距最近一次连接的秒数，精确到微秒
+    Relative,
+    /// 距上一条收发数据的秒数，精确到微秒
+    Delta,
+}
+impl TimeFormat {
+    pub const ALL: [TimeFormat; 3] = [TimeFormat::Wall, TimeFormat::Relative, TimeFormat::Delta];
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeFormat::Wall => "本地时间",
+            TimeFormat::Relative => "连接后时间",
+            TimeFormat::Delta => "帧间隔",
+        }
+    }
+    /// 相对时间和间隔都按单调时钟计算，`connected_at` 为连接时的单调时钟微秒数
+    pub fn format(&self, record: &Record, prev: Option<&Record>, connected_at: u64) -> String {
+        let seconds = |micros: i64| {
+            format!(
+                "{}{}.{:06}",
+                if micros < 0 { "-" } else { "" },
+                micros.unsigned_abs() / 1_000_000,
+                micros.unsigned_abs() % 1_000_000
+            )
+        };
+        match self {
+            TimeFormat::Wall => DateTime::<Local>::from(record.time)
+                .format("%H:%M:%S%.3f")
+                .to_string(),
+            TimeFormat::Relative => seconds(record.mono as i64 - connected_at as i64),
+            TimeFormat::Delta => {
+                let prev = prev.map_or(record.mono, |prev| prev.mono);
+                format!("+{}", seconds(record.mono as i64 - prev as i64))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_display_modes() {
+    let bytes = b"OK\r\n\x00\xff\\";
+    let format = |mode: DisplayMode| mode.format(bytes, TextEncoding::Utf8);
+    assert_eq!(format(DisplayMode::Hex), "4F 4B 0D 0A 00 FF 5C");
+    assert_eq!(format(DisplayMode::Text), "OK\\r\\n\\x00\\xFF\\\\");
+    assert_eq!(
+        format(DisplayMode::HexDump),
+        "4F 4B 0D 0A 00 FF 5C  |OK....\\|"
+    );
+    assert_eq!(format(DisplayMode::Decimal), "79 75 13 10 0 255 92");
+}
+
+#[test]
+fn test_display_highlight() {
+    let bytes = b"AB\r\nC";
+    let ranges = std::slice::from_ref(&(1..3));
+    let highlight = |mode: DisplayMode| mode.highlight(bytes, TextEncoding::Utf8, ranges);
+    let segments = |parts: &[(&str, bool)]| {
+        parts
+            .iter()
+            .map(|(text, h)| (text.to_string(), *h))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        highlight(DisplayMode::Hex),
+        segments(&[("41 ", false), ("42 0D", true), (" 0A 43", false)])
+    );
+    assert_eq!(
+        highlight(DisplayMode::HexDump),
+        segments(&[
+            ("41 ", false),
+            ("42 0D", true),
+            (" 0A 43  |A", false),
+            ("B.", true),
+            (".C|", false)
+        ])
+    );
+    assert_eq!(
+        highlight(DisplayMode::Text),
+        segments(&[("A", false), ("B\\r", true), ("\\nC", false)])
+    );
+    assert!(DisplayMode::Decimal
+        .highlight(bytes, TextEncoding::Utf8, &[])
+        .iter()
+        .all(|(_, h)| !h));
+}
+
+#[test]
+fn test_time_formats() {
+    use crate::record::Direction;
+
+    let mut prev = Record::new(Direction::Rx, Vec::new());
+    prev.mono = 1_000_000;
+    let mut record = prev.clone();
+    record.mono = 3_500_250;
+    assert_eq!(
+        TimeFormat::Relative.format(&record, None, 1_500_000),
+        "2.000250"
+    );
+    assert_eq!(
+        TimeFormat::Relative.format(&prev, None, 1_500_000),
+        "-0.500000"
+    );
+    assert_eq!(
+        TimeFormat::Delta.format(&record, Some(&prev), 0),
+        "+2.500250"
+    );
+    assert_eq!(TimeFormat::Delta.format(&record, None, 0), "+0.000000");
+    assert_eq!(
+        TimeFormat::Wall.format(&record, None, 0).len(),
+        "00:00:00.000".len()
+    );
+}
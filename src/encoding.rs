@@ -0,0 +1,171 @@
+use std::fmt::Write;
+
+use encoding_rs::{DecoderResult, Encoding, GB18030, UTF_16LE, UTF_8};
+
+/// 文本显示和文本发送使用的字符编码
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextEncoding {
+    Utf8,
+    /// GBK 是 GB18030 的子集，按 GB18030 解码和编码
+    Gb18030,
+    Latin1,
+    Utf16Le,
+}
+impl TextEncoding {
+    pub const ALL: [TextEncoding; 4] = [
+        TextEncoding::Utf8,
+        TextEncoding::Gb18030,
+        TextEncoding::Latin1,
+        TextEncoding::Utf16Le,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Gb18030 => "GBK/GB18030",
+            TextEncoding::Latin1 => "Latin-1",
+            TextEncoding::Utf16Le => "UTF-16LE",
+        }
+    }
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        match self {
+            TextEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            TextEncoding::Gb18030 => Ok(GB18030.encode(text).0.into_owned()),
+            TextEncoding::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(c).map_err(|_| format!("字符 {} 无法用 Latin-1 编码", c)))
+                .collect(),
+            // encoding_rs 不输出 UTF-16，直接转换
+            TextEncoding::Utf16Le => Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+        }
+    }
+    /// 解码成原样的文本，无法解码的字节替换成 U+FFFD
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            TextEncoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            TextEncoding::Gb18030 => GB18030.decode_without_bom_handling(bytes).0.into_owned(),
+            TextEncoding::Utf16Le => UTF_16LE.decode_without_bom_handling(bytes).0.into_owned(),
+            TextEncoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+        }
+    }
+    /// 同 `decode`，另外返回文本中每个字节对应的原始字节位置，末尾多一项为 `bytes.len()`
+    pub fn decode_indexed(&self, bytes: &[u8]) -> (String, Vec<usize>) {
+        let mut text = String::with_capacity(bytes.len());
+        let mut index = Vec::with_capacity(bytes.len() + 1);
+        let encoding = match self {
+            TextEncoding::Utf8 => UTF_8,
+            TextEncoding::Gb18030 => GB18030,
+            TextEncoding::Utf16Le => UTF_16LE,
+            TextEncoding::Latin1 => {
+                for (i, &b) in bytes.iter().enumerate() {
+                    text.push(b as char);
+                    index.resize(text.len(), i);
+                }
+                index.push(bytes.len());
+                return (text, index);
+            }
+        };
+        // 逐字节喂给解码器，输出的字符对应从上次输出之后开始的字节
+        let mut decoder = encoding.new_decoder_without_bom_handling();
+        let mut start = 0;
+        for i in 0..bytes.len() {
+            let last = i + 1 == bytes.len();
+            text.reserve(decoder.max_utf8_buffer_length(1).unwrap_or(16));
+            let _ = decoder.decode_to_string(&bytes[i..=i], &mut text, last);
+            if text.len() > index.len() {
+                index.resize(text.len(), start);
+                start = i + 1;
+            }
+        }
+        index.push(bytes.len());
+        (text, index)
+    }
+    /// 解码成便于单行显示的文本，控制字符和无法解码的字节都转义成 `\r` `\xNN` 这样的形式
+    pub fn escape(&self, bytes: &[u8]) -> String {
+        let mut text = String::with_capacity(bytes.len());
+        match self {
+            TextEncoding::Utf8 => decode_escaped(UTF_8, bytes, &mut text),
+            TextEncoding::Gb18030 => decode_escaped(GB18030, bytes, &mut text),
+            TextEncoding::Utf16Le => decode_escaped(UTF_16LE, bytes, &mut text),
+            // Latin-1 每个字节就是同值的 Unicode 字符
+            TextEncoding::Latin1 => bytes.iter().for_each(|&b| push_char(&mut text, b as char)),
+        }
+        text
+    }
+}
+
+fn push_char(text: &mut String, c: char) {
+    match c {
+        '\r' => text.push_str("\\r"),
+        '\n' => text.push_str("\\n"),
+        '\t' => text.push_str("\\t"),
+        '\\' => text.push_str("\\\\"),
+        c if c.is_control() => {
+            let _ = write!(text, "\\x{:02X}", c as u32);
+        }
+        c => text.push(c),
+    }
+}
+
+fn push_invalid(text: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        let _ = write!(text, "\\x{:02X}", b);
+    }
+}
+
+fn decode_escaped(encoding: &'static Encoding, bytes: &[u8], text: &mut String) {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut input = bytes;
+    let mut buf = String::with_capacity(bytes.len() * 3 + 4);
+    loop {
+        buf.clear();
+        let (result, read) = decoder.decode_to_string_without_replacement(input, &mut buf, true);
+        buf.chars().for_each(|c| push_char(text, c));
+        match result {
+            DecoderResult::InputEmpty => break,
+            DecoderResult::OutputFull => buf.reserve(input.len() * 3 + 4),
+            // 出错的字节在已读部分的末尾，后面还有 `after` 个已读字节由解码器自己保留
+            DecoderResult::Malformed(bad, after) => {
+                let end = read.saturating_sub(after as usize);
+                push_invalid(text, &input[end.saturating_sub(bad as usize)..end]);
+            }
+        }
+        input = &input[read..];
+    }
+}
+
+#[test]
+fn test_encoding_escape() {
+    assert_eq!(
+        TextEncoding::Utf8.escape("中文\r\n".as_bytes()),
+        "中文\\r\\n"
+    );
+    assert_eq!(TextEncoding::Utf8.escape(b"A\xffB\x00"), "A\\xFFB\\x00");
+    let gbk = [0xD6, 0xD0, 0xCE, 0xC4, 0xFF, 0x41, 0x81, 0x30, 0x41];
+    assert_eq!(TextEncoding::Gb18030.escape(&gbk), "中文\\xFFA\\x810A");
+    assert_eq!(TextEncoding::Latin1.escape(b"caf\xe9"), "café");
+    assert_eq!(TextEncoding::Utf16Le.escape(&[0x2D, 0x4E, 0x41]), "中\\x41");
+}
+
+#[test]
+fn test_encoding_encode() {
+    assert_eq!(
+        TextEncoding::Gb18030.encode("中文").unwrap(),
+        vec![0xD6, 0xD0, 0xCE, 0xC4]
+    );
+    assert_eq!(TextEncoding::Latin1.encode("café").unwrap(), b"caf\xe9");
+    assert!(TextEncoding::Latin1.encode("中").is_err());
+    assert_eq!(
+        TextEncoding::Utf16Le.encode("中A").unwrap(),
+        vec![0x2D, 0x4E, 0x41, 0x00]
+    );
+}
+
+#[test]
+fn test_encoding_decode_indexed() {
+    let (text, index) = TextEncoding::Gb18030.decode_indexed(&[0x41, 0xD6, 0xD0, 0x42]);
+    assert_eq!(text, "A中B");
+    assert_eq!(index, vec![0, 1, 1, 1, 3, 4]);
+    let (text, index) = TextEncoding::Latin1.decode_indexed(b"\xe9A");
+    assert_eq!(text, "éA");
+    assert_eq!(index, vec![0, 0, 1, 2]);
+}
@@ -0,0 +1,184 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+
+use eframe::egui;
+use tokio_serial::SerialPortInfo;
+
+use crate::record::Record;
+use crate::serial::ModemLines;
+
+/// 通讯任务发给界面的事件
+#[derive(Debug)]
+pub enum Event {
+    RxFrame(Record),
+    TxFrame(Record),
+    /// `Some` 为子连接，比如 TCP server 的客户端
+    Connected(Option<String>),
+    Disconnected(Option<String>),
+    Error(String),
+    PortListChanged(Vec<SerialPortInfo>),
+    /// 串口输入状态线变化
+    ModemChanged(ModemLines),
+}
+
+/// 排队等界面处理的数据帧上限，界面不刷新（比如窗口最小化）时超出的帧直接丢弃，
+/// 避免绕过历史记录的上限占用内存。状态类事件数量很少，不受限制，也不会丢
+const MAX_QUEUED_FRAMES: usize = 10_000;
+
+/// 发送端和接收端共享的排队计数
+#[derive(Default)]
+struct Backlog {
+    queued: AtomicUsize,
+    dropped: AtomicUsize,
+}
+impl Backlog {
+    /// 有丢弃的帧时生成一条错误事件，并清零计数
+    fn take_dropped(&self) -> Option<Event> {
+        match self.dropped.swap(0, Ordering::Relaxed) {
+            0 => None,
+            n => Some(Event::Error(format!("界面处理不过来，丢弃了 {} 帧数据", n))),
+        }
+    }
+}
+
+fn is_frame(event: &Event) -> bool {
+    matches!(event, Event::RxFrame(_) | Event::TxFrame(_))
+}
+
+/// 事件带上发送者的连接编号，界面据此忽略已关闭连接的状态变化
+pub struct EventReceiver {
+    rx: mpsc::Receiver<(usize, Event)>,
+    backlog: Arc<Backlog>,
+}
+impl EventReceiver {
+    /// 队列取空后，还没报告过的丢帧作为一条错误事件返回
+    pub fn try_recv(&self) -> Result<(usize, Event), mpsc::TryRecvError> {
+        match self.rx.try_recv() {
+            Ok((conn, event)) => {
+                if is_frame(&event) {
+                    self.backlog.queued.fetch_sub(1, Ordering::Relaxed);
+                }
+                Ok((conn, event))
+            }
+            Err(e) => match self.backlog.take_dropped() {
+                Some(event) => Ok((0, event)),
+                None => Err(e),
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EventSender {
+    conn: usize,
+    tx: mpsc::Sender<(usize, Event)>,
+    backlog: Arc<Backlog>,
+    /// 有界面时发送事件后唤醒界面刷新
+    ctx: Option<egui::Context>,
+}
+impl EventSender {
+    pub fn channel(ctx: Option<egui::Context>) -> (EventSender, EventReceiver) {
+        let (tx, rx) = mpsc::channel();
+        let backlog = Arc::new(Backlog::default());
+        let sender = EventSender {
+            conn: 0,
+            tx,
+            backlog: backlog.clone(),
+            ctx,
+        };
+        (sender, EventReceiver { rx, backlog })
+    }
+    pub fn with_conn(&self, conn: usize) -> EventSender {
+        EventSender {
+            conn,
+            ..self.clone()
+        }
+    }
+    /// 收发的记录在这里带上连接编号
+    pub fn send(&self, mut event: Event) {
+        if let Event::RxFrame(record) | Event::TxFrame(record) = &mut event {
+            record.conn = self.conn;
+        }
+        if is_frame(&event) {
+            if self.backlog.queued.fetch_add(1, Ordering::Relaxed) >= MAX_QUEUED_FRAMES {
+                self.backlog.queued.fetch_sub(1, Ordering::Relaxed);
+                self.backlog.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            // 恢复后先报告丢了多少帧，在历史记录中的位置和实际丢帧的位置一致
+            if let Some(dropped) = self.backlog.take_dropped() {
+                let _ = self.tx.send((self.conn, dropped));
+            }
+        }
+        // 界面已经退出时没有接收者，事件直接丢弃
+        let _ = self.tx.send((self.conn, event));
+        if let Some(ctx) = self.ctx.as_ref() {
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// 测试用，收取事件直到 `done` 返回 true，返回期间收到的所有事件
+#[cfg(test)]
+pub async fn wait_for(events_rx: &EventReceiver, done: impl Fn(&Event) -> bool) -> Vec<Event> {
+    use std::time::{Duration, Instant};
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut received = Vec::new();
+    loop {
+        match events_rx.try_recv() {
+            Ok((_, event)) => {
+                let finished = done(&event);
+                received.push(event);
+                if finished {
+                    return received;
+                }
+            }
+            Err(_) => {
+                assert!(Instant::now() < deadline, "timed out: {:?}", received);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_frame_backlog() {
+    use crate::record::Direction;
+
+    let (events, events_rx) = EventSender::channel(None);
+    let frame = |i: usize| Event::RxFrame(Record::new(Direction::Rx, i.to_le_bytes().to_vec()));
+    for i in 0..MAX_QUEUED_FRAMES + 5 {
+        events.send(frame(i));
+    }
+    // 状态事件不受上限影响
+    events.send(Event::Disconnected(None));
+    for _ in 0..MAX_QUEUED_FRAMES {
+        assert!(matches!(events_rx.try_recv(), Ok((0, Event::RxFrame(_)))));
+    }
+    assert!(matches!(
+        events_rx.try_recv(),
+        Ok((0, Event::Disconnected(None)))
+    ));
+    // 队列取空后报告丢弃的帧
+    let Ok((_, Event::Error(e))) = events_rx.try_recv() else {
+        panic!("expected dropped frames error");
+    };
+    assert!(e.contains("丢弃了 5 帧"));
+    assert!(events_rx.try_recv().is_err());
+
+    // 有空间后下一帧之前先报告丢帧
+    for i in 0..MAX_QUEUED_FRAMES + 2 {
+        events.send(frame(i));
+    }
+    events_rx.try_recv().unwrap();
+    events.send(frame(0));
+    let received: Vec<Event> = std::iter::from_fn(|| events_rx.try_recv().ok())
+        .map(|(_, event)| event)
+        .collect();
+    assert_eq!(received.len(), MAX_QUEUED_FRAMES + 1);
+    assert!(
+        matches!(&received[MAX_QUEUED_FRAMES - 1], Event::Error(e) if e.contains("丢弃了 2 帧"))
+    );
+    assert!(matches!(received.last(), Some(Event::RxFrame(_))));
+}
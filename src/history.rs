@@ -0,0 +1,342 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::checksum::FrameStatus;
+use crate::record::{Direction, Record};
+
+/// 每个磁盘段文件最多保存的记录数
+const SEGMENT_RECORDS: usize = 10_000;
+/// 最多同时保持打开用于读取的段文件数
+const MAX_OPEN_SEGMENTS: usize = 16;
+/// 每次重新开始写磁盘都用新的目录，避免和正在删除的旧目录冲突
+static STORE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// 数据区的历史记录，内存中只保留最新的一部分，超出上限的旧记录可以写入磁盘
+pub struct History {
+    records: VecDeque<Record>,
+    /// 内存中所有记录的数据字节数
+    bytes: usize,
+    pub max_records: usize,
+    pub max_bytes: usize,
+    pub spill: bool,
+    store: Option<SpillStore>,
+    /// 已经丢弃的记录数，`dropped + 序号` 是记录不随淘汰变化的编号
+    dropped: usize,
+    /// 读写磁盘的错误，由界面取走显示在状态栏，读取时只有 `&self` 所以用 `RefCell`
+    error: RefCell<Option<String>>,
+}
+impl History {
+    pub fn new(max_records: usize, max_bytes: usize) -> History {
+        History {
+            records: VecDeque::new(),
+            bytes: 0,
+            max_records,
+            max_bytes,
+            spill: false,
+            store: None,
+            dropped: 0,
+            error: RefCell::new(None),
+        }
+    }
+    pub fn push(&mut self, record: Record) {
+        self.bytes += record.bytes.len();
+        self.records.push_back(record);
+        self.evict();
+    }
+    /// 按当前上限淘汰最旧的记录，修改上限后也需要调用
+    pub fn evict(&mut self) {
+        if !self.spill {
+            self.dropped += self.spilled();
+            self.store = None;
+        }
+        while self.records.len() > self.max_records.max(1)
+            || (self.bytes > self.max_bytes && self.records.len() > 1)
+        {
+            let Some(record) = self.records.pop_front() else {
+                break;
+            };
+            self.bytes -= record.bytes.len();
+            if !self.spill {
+                self.dropped += 1;
+            } else if let Err(e) = self.spill_record(&record) {
+                self.set_error(format!("写入磁盘失败，已停止写入并丢弃磁盘中的记录: {}", e));
+                self.spill = false;
+                self.dropped += self.spilled() + 1;
+                self.store = None;
+            }
+        }
+    }
+    fn spill_record(&mut self, record: &Record) -> io::Result<()> {
+        if self.store.is_none() {
+            self.store = Some(SpillStore::new()?);
+        }
+        self.store.as_mut().unwrap().append(record)
+    }
+    /// 磁盘中的记录数
+    pub fn spilled(&self) -> usize {
+        self.store.as_ref().map_or(0, |store| store.index.len())
+    }
+    pub fn len(&self) -> usize {
+        self.spilled() + self.records.len()
+    }
+    /// 按时间顺序取第 `index` 条记录，写入磁盘的部分需要读文件
+    pub fn get(&self, index: usize) -> Option<Cow<'_, Record>> {
+        let spilled = self.spilled();
+        if index < spilled {
+            match self.store.as_ref()?.read(index) {
+                Ok(record) => Some(Cow::Owned(record)),
+                Err(e) => {
+                    self.set_error(format!("读取磁盘中的记录失败: {}", e));
+                    None
+                }
+            }
+        } else {
+            self.records.get(index - spilled).map(Cow::Borrowed)
+        }
+    }
+    /// 第 `index` 条之前最近的一条收发数据，跳过提示信息
+    pub fn prev_frame(&self, index: usize) -> Option<Cow<'_, Record>> {
+        (0..index)
+            .rev()
+            .filter_map(|i| self.get(i))
+            .find(|record| record.direction != Direction::Info)
+    }
+    fn set_error(&self, error: String) {
+        *self.error.borrow_mut() = Some(error);
+    }
+    pub fn take_error(&self) -> Option<String> {
+        self.error.borrow_mut().take()
+    }
+    pub fn has_error(&self) -> bool {
+        self.error.borrow().is_some()
+    }
+    /// 当前第一条记录的编号
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+    pub fn clear(&mut self) {
+        self.dropped += self.len();
+        self.records.clear();
+        self.bytes = 0;
+        self.store = None;
+    }
+}
+
+/// 写入磁盘的记录，按段分文件保存，内存中只保留每条记录的位置
+struct SpillStore {
+    dir: PathBuf,
+    /// 每条记录所在的段和偏移
+    index: Vec<(usize, u64)>,
+    segment: File,
+    segment_len: u64,
+    /// 读取用的段文件，搜索时会依次读很多条记录，不用每次都重新打开
+    readers: RefCell<HashMap<usize, BufReader<File>>>,
+}
+impl SpillStore {
+    fn new() -> io::Result<SpillStore> {
+        let dir = std::env::temp_dir().join(format!(
+            "byte_watcher_{}_{}",
+            std::process::id(),
+            STORE_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        let segment = Self::open_segment(&dir, 0)?;
+        Ok(SpillStore {
+            dir,
+            index: Vec::new(),
+            segment,
+            segment_len: 0,
+            readers: RefCell::new(HashMap::new()),
+        })
+    }
+    fn segment_path(dir: &std::path::Path, segment: usize) -> PathBuf {
+        dir.join(format!("{:06}.seg", segment))
+    }
+    fn open_segment(dir: &std::path::Path, segment: usize) -> io::Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Self::segment_path(dir, segment))
+    }
+    fn append(&mut self, record: &Record) -> io::Result<()> {
+        let segment = self.index.len() / SEGMENT_RECORDS;
+        if self.index.len().is_multiple_of(SEGMENT_RECORDS) && !self.index.is_empty() {
+            self.segment = Self::open_segment(&self.dir, segment)?;
+            self.segment_len = 0;
+        }
+        let buf = encode(record);
+        self.segment.write_all(&buf)?;
+        self.index.push((segment, self.segment_len));
+        self.segment_len += buf.len() as u64;
+        Ok(())
+    }
+    fn read(&self, index: usize) -> io::Result<Record> {
+        let (segment, offset) = self.index[index];
+        let mut readers = self.readers.borrow_mut();
+        if !readers.contains_key(&segment) && readers.len() >= MAX_OPEN_SEGMENTS {
+            readers.clear();
+        }
+        let reader = match readers.entry(segment) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let file = File::open(Self::segment_path(&self.dir, segment))?;
+                entry.insert(BufReader::new(file))
+            }
+        };
+        reader.seek(SeekFrom::Start(offset))?;
+        decode(reader)
+    }
+}
+impl Drop for SpillStore {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn encode(record: &Record) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(record.bytes.len() + 32);
+    buf.push(match record.direction {
+        Direction::Rx => 0,
+        Direction::Tx => 1,
+        Direction::Info => 2,
+    });
+    buf.push(match record.status {
+        FrameStatus::Unchecked => 0,
+        FrameStatus::Ok => 1,
+        FrameStatus::Bad => 2,
+    });
+    let micros = record
+        .time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64;
+    buf.extend_from_slice(&micros.to_le_bytes());
+    buf.extend_from_slice(&record.mono.to_le_bytes());
+    buf.extend_from_slice(&(record.conn as u64).to_le_bytes());
+    for label in [&record.source, &record.tag] {
+        let label = label.as_deref().unwrap_or("");
+        buf.extend_from_slice(&(label.len() as u16).to_le_bytes());
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.extend_from_slice(&(record.bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&record.bytes);
+    buf
+}
+
+fn decode(reader: &mut impl Read) -> io::Result<Record> {
+    fn read_vec(reader: &mut impl Read, len: usize) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0; len];
+        reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+    fn read_label(reader: &mut impl Read) -> io::Result<Option<String>> {
+        let mut len = [0; 2];
+        reader.read_exact(&mut len)?;
+        let label = read_vec(reader, u16::from_le_bytes(len) as usize)?;
+        Ok((!label.is_empty()).then(|| String::from_utf8_lossy(&label).into_owned()))
+    }
+    let mut head = [0; 26];
+    reader.read_exact(&mut head)?;
+    let direction = match head[0] {
+        0 => Direction::Rx,
+        1 => Direction::Tx,
+        _ => Direction::Info,
+    };
+    let mut record = Record::new(direction, Vec::new());
+    record.status = match head[1] {
+        1 => FrameStatus::Ok,
+        2 => FrameStatus::Bad,
+        _ => FrameStatus::Unchecked,
+    };
+    let micros = u64::from_le_bytes(head[2..10].try_into().unwrap());
+    record.time = UNIX_EPOCH + Duration::from_micros(micros);
+    record.mono = u64::from_le_bytes(head[10..18].try_into().unwrap());
+    record.conn = u64::from_le_bytes(head[18..26].try_into().unwrap()) as usize;
+    record.source = read_label(reader)?;
+    record.tag = read_label(reader)?;
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    record.bytes = read_vec(reader, u32::from_le_bytes(len) as usize)?;
+    Ok(record)
+}
+
+#[test]
+fn test_history_spill() {
+    let mut history = History::new(3, usize::MAX);
+    history.spill = true;
+    let mut monos = Vec::new();
+    for i in 0..10u8 {
+        let record = Record::new(Direction::Rx, vec![i; i as usize])
+            .with_source("127.0.0.1:8080")
+            .with_conn(i as usize);
+        monos.push(record.mono);
+        history.push(record);
+    }
+    assert_eq!(history.len(), 10);
+    assert_eq!(history.spilled(), 7);
+    assert_eq!(history.len() - history.spilled(), 3);
+    for i in 0..10u8 {
+        let record = history.get(i as usize).unwrap();
+        assert_eq!(record.bytes, vec![i; i as usize]);
+        assert_eq!(record.source.as_deref(), Some("127.0.0.1:8080"));
+        assert_eq!(record.tag, None);
+        assert_eq!(record.mono, monos[i as usize]);
+        assert_eq!(record.conn, i as usize);
+    }
+    history.clear();
+    assert_eq!(history.len(), 0);
+}
+
+#[test]
+fn test_history_byte_budget() {
+    let mut history = History::new(100, 10);
+    for _ in 0..5 {
+        history.push(Record::new(Direction::Rx, vec![0; 4]));
+    }
+    // 不写磁盘时超出预算的记录直接丢弃
+    assert_eq!(history.len(), 2);
+    assert_eq!(history.spilled(), 0);
+    assert_eq!(history.dropped(), 3);
+    history.clear();
+    assert_eq!(history.dropped(), 5);
+}
+
+#[test]
+fn test_history_spill_error() {
+    let mut history = History::new(1, usize::MAX);
+    history.spill = true;
+    for i in 0..3u8 {
+        history.push(Record::new(Direction::Rx, vec![i]));
+    }
+    assert!(history.take_error().is_none());
+    // 临时目录被清理后读不到记录，错误交给界面显示
+    fs::remove_dir_all(&history.store.as_ref().unwrap().dir).unwrap();
+    assert!(history.get(0).is_none());
+    assert!(history.has_error());
+    assert!(history
+        .take_error()
+        .unwrap()
+        .starts_with("读取磁盘中的记录失败"));
+    assert!(!history.has_error());
+    assert_eq!(history.get(2).unwrap().bytes, vec![2]);
+}
+
+#[test]
+fn test_history_prev_frame() {
+    let mut history = History::new(100, usize::MAX);
+    history.push(Record::new(Direction::Rx, vec![1]));
+    history.push(Record::message("已连接"));
+    history.push(Record::new(Direction::Tx, vec![2]));
+    history.push(Record::message("GAP"));
+    history.push(Record::new(Direction::Rx, vec![3]));
+    assert_eq!(history.prev_frame(4).unwrap().bytes, vec![2]);
+    assert_eq!(history.prev_frame(2).unwrap().bytes, vec![1]);
+    assert!(history.prev_frame(0).is_none());
+}
@@ -1,22 +1,52 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 #![allow(rustdoc::missing_crate_level_docs)]
-#[macro_use] // 必须添加此属性
-extern crate lazy_static; // 显式声明宏导入:ml-citation{ref="1,8" data="citationList"}
 
+mod checksum;
+mod codec;
+mod display;
+mod encoding;
+mod event;
+mod history;
+mod record;
+mod search;
 mod serial;
+mod tcp;
+mod tcp_server;
+mod transport;
+mod udp;
+mod ws;
+use checksum::{Algorithm, ChecksumSpec, FrameStatus};
+use codec::{DelimiterPosition, FrameCodec, Framing, LengthField};
+use display::{DisplayMode, TimeFormat};
 use eframe::egui;
 use eframe::epaint::text::{FontData, FontDefinitions};
 use eframe::epaint::FontFamily;
-use serial::Serial;
-use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::time;
+use encoding::TextEncoding;
+use event::{Event, EventReceiver, EventSender};
+use history::History;
+use record::{monotonic_micros, Direction, Record};
+use search::{Search, SearchKind};
+use serial::{port_label, usb_serial_number, Control, Line, ModemLines, Serial};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tcp::Tcp;
+use tcp_server::TcpServer;
+use tokio_serial::{DataBits, FlowControl, Parity, SerialPortBuilder, SerialPortInfo, StopBits};
+use transport::{Outgoing, Status, Transport};
+use udp::Udp;
+use ws::Ws;
 
-lazy_static! {
-    static ref SERIALS: Arc<Mutex<HashMap<String, String>>> = Arc::new(Mutex::new(HashMap::new()));
-    static ref DATA: Arc<Mutex<VecDeque<String>>> =
-        Arc::new(Mutex::new(VecDeque::with_capacity(2000)));
+pub fn to_hex(bytes: &[u8]) -> String {
+    hex::encode(bytes)
+        .chars()
+        .enumerate()
+        .flat_map(|(i, c)| {
+            if i > 0 && i % 2 == 0 { Some(' ') } else { None }
+                .into_iter()
+                .chain(std::iter::once(c.to_ascii_uppercase()))
+        })
+        .collect()
 }
 
 #[tokio::main]
@@ -44,22 +74,6 @@ async fn main() -> eframe::Result {
         ..Default::default()
     };
 
-    tokio::spawn(async {
-        let mut interval = time::interval(time::Duration::from_secs(1));
-        loop {
-            interval.tick().await;
-            match tokio_serial::available_ports() {
-                Ok(ports) => {
-                    let mut serials = SERIALS.lock().unwrap();
-                    serials.clear();
-                    for port in ports {
-                        serials.insert(port.port_name.clone(), port.port_name);
-                    }
-                }
-                Err(_) => {}
-            }
-        }
-    });
     eframe::run_native(
         "byte watcher",
         options,
@@ -67,7 +81,7 @@ async fn main() -> eframe::Result {
             // This gives us image support:
             egui_extras::install_image_loaders(&cc.egui_ctx);
             setup_fonts(&cc.egui_ctx);
-            Ok(Box::<ByteWatcherApp>::default())
+            Ok(Box::new(ByteWatcherApp::new(&cc.egui_ctx)))
         }),
     )
 }
@@ -105,24 +119,244 @@ fn setup_fonts(ctx: &egui::Context) {
 pub enum ConnectType {
     SERIAL,
     TCP,
+    TCPSERVER,
     UDP,
     WS,
 }
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FramingMode {
+    RAW,
+    DELIMITER,
+    FIXED,
+    IDLE,
+    LENGTH,
+}
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SendMode {
+    HEX,
+    TEXT,
+}
 
 pub struct ByteWatcherApp {
     connected: bool,
     connect_type: ConnectType,
     serial_connetct_info: SerialInfo,
-    serial: Option<Serial>,
+    tcp_connect_info: TcpInfo,
+    tcp_server_info: TcpInfo,
+    udp_connect_info: UdpInfo,
+    ws_connect_info: WsInfo,
+    transport: Option<Box<dyn Transport>>,
+    framing_info: FramingInfo,
+    rx_checksum: ChecksumInfo,
+    tx_checksum: ChecksumInfo,
+    send_mode: SendMode,
+    send_text: String,
+    send_target: Option<SocketAddr>,
+    history: History,
+    /// 串口扫描任务发来的可用串口
+    ports: Vec<SerialPortInfo>,
+    events: EventSender,
+    events_rx: EventReceiver,
+    /// 每次连接分配新的编号，旧连接的断开事件不影响当前连接
+    conn_id: usize,
+    /// 最近一次的错误，显示在状态栏
+    last_error: Option<String>,
+    modem_info: ModemInfo,
+    /// 串口任务轮询到的输入状态线，未连接时为 `None`
+    modem_lines: Option<ModemLines>,
+    break_info: BreakInfo,
+    /// 串口意外断开后等待重连
+    reconnect: Option<Reconnect>,
+    display_mode: DisplayMode,
+    encoding: TextEncoding,
+    time_format: TimeFormat,
+    /// 最近一次连接时的单调时钟微秒数
+    connected_at: u64,
+    search: Search,
+}
+pub struct Reconnect {
+    lost_at: Instant,
+    attempt_at: Option<Instant>,
+}
+#[derive(PartialEq, Debug)]
+enum ReconnectAction {
+    /// 自动重连已关闭，不再等待
+    Cancel,
+    Wait,
+    Attempt,
+}
+impl Reconnect {
+    /// 连接意外断开后是否等待重连，只有开启了自动重连的串口才等
+    fn after_loss(was_serial: bool, info: &SerialInfo, now: Instant) -> Option<Reconnect> {
+        (was_serial && info.auto_reconnect).then_some(Reconnect {
+            lost_at: now,
+            attempt_at: None,
+        })
+    }
+    /// 距上次尝试超过间隔，并且设备重新出现在串口列表中时才尝试
+    fn check(&self, info: &SerialInfo, ports: &[SerialPortInfo], now: Instant) -> ReconnectAction {
+        if !info.auto_reconnect {
+            return ReconnectAction::Cancel;
+        }
+        let waiting = self
+            .attempt_at
+            .is_some_and(|at| now.duration_since(at) < RECONNECT_INTERVAL);
+        match waiting || info.lost(ports) {
+            true => ReconnectAction::Wait,
+            false => ReconnectAction::Attempt,
+        }
+    }
+}
+pub struct BreakInfo {
+    before_send: bool,
+    ms: u64,
+}
+pub struct ModemInfo {
+    dtr: bool,
+    rts: bool,
+    pulse_line: Line,
+    pulse_ms: u64,
 }
 pub struct SerialInfo {
     path: String,
     baud_rate: u32,
     data_bits: u8,
     stop_bits: u8,
+    /// 串口库只支持无校验、奇校验和偶校验，没有 Mark/Space
+    parity: Parity,
+    flow_control: FlowControl,
+    /// 选中的 USB 串口的序列号
+    serial_number: Option<String>,
+    match_serial_number: bool,
+    auto_reconnect: bool,
+}
+impl SerialInfo {
+    /// 串口列表变化后按序列号找回设备，重新插拔后串口号可能变化
+    fn resolve(&mut self, ports: &[SerialPortInfo]) {
+        if !self.match_serial_number {
+            return;
+        }
+        let Some(serial_number) = self.serial_number.as_deref() else {
+            return;
+        };
+        if let Some(port) = ports
+            .iter()
+            .find(|port| usb_serial_number(port) == Some(serial_number))
+        {
+            self.path = port.port_name.clone();
+        }
+    }
+    /// 串口号不在列表中，说明设备已经拔掉
+    fn lost(&self, ports: &[SerialPortInfo]) -> bool {
+        !ports.iter().any(|port| port.port_name == self.path)
+    }
+    fn to_builder(&self) -> Result<SerialPortBuilder, String> {
+        if self.path.is_empty() {
+            return Err("请选择串口".to_string());
+        }
+        let data_bits = DataBits::try_from(self.data_bits)
+            .map_err(|_| format!("不支持的数据位: {}", self.data_bits))?;
+        let stop_bits = StopBits::try_from(self.stop_bits)
+            .map_err(|_| format!("不支持的停止位: {}", self.stop_bits))?;
+        Ok(tokio_serial::new(&self.path, self.baud_rate)
+            .data_bits(data_bits)
+            .stop_bits(stop_bits)
+            .parity(self.parity)
+            .flow_control(self.flow_control))
+    }
+}
+pub struct TcpInfo {
+    host: String,
+    port: u16,
+}
+pub struct UdpInfo {
+    bind_host: String,
+    bind_port: u16,
+    remote: String,
+}
+pub struct WsInfo {
+    url: String,
 }
-impl Default for ByteWatcherApp {
-    fn default() -> Self {
+pub struct FramingInfo {
+    mode: FramingMode,
+    delimiter: String,
+    position: DelimiterPosition,
+    fixed_len: usize,
+    idle_ms: u64,
+    header: String,
+    length_offset: usize,
+    length_width: usize,
+    length_big_endian: bool,
+    length_includes_header: bool,
+    length_includes_crc: bool,
+    crc_len: usize,
+}
+impl FramingInfo {
+    fn to_codec(&self) -> Result<FrameCodec, String> {
+        let framing = match self.mode {
+            FramingMode::RAW => Framing::Raw,
+            FramingMode::DELIMITER => Framing::Delimiter {
+                delimiter: parse_pattern(&self.delimiter, "分隔符")?,
+                position: self.position,
+            },
+            FramingMode::FIXED => {
+                if self.fixed_len == 0 {
+                    return Err("帧长度不能为 0".into());
+                }
+                Framing::Fixed(self.fixed_len)
+            }
+            FramingMode::IDLE => Framing::Idle(Duration::from_millis(self.idle_ms.max(1))),
+            FramingMode::LENGTH => {
+                // 长度字段的值没有覆盖到的部分由 adjustment 补上
+                let mut adjustment = 0;
+                if !self.length_includes_header {
+                    adjustment += self.length_offset + self.length_width;
+                }
+                if !self.length_includes_crc {
+                    adjustment += self.crc_len;
+                }
+                Framing::Length(LengthField {
+                    header: parse_pattern(&self.header, "帧头")?,
+                    offset: self.length_offset,
+                    width: self.length_width,
+                    big_endian: self.length_big_endian,
+                    adjustment: adjustment as isize,
+                })
+            }
+        };
+        Ok(FrameCodec::new(framing))
+    }
+}
+pub struct ChecksumInfo {
+    enabled: bool,
+    algorithm: Algorithm,
+    width: usize,
+    start: usize,
+    trailer: usize,
+    big_endian: bool,
+}
+impl ChecksumInfo {
+    fn to_spec(&self) -> Option<ChecksumSpec> {
+        self.enabled.then_some(ChecksumSpec {
+            algorithm: self.algorithm,
+            width: self.width,
+            start: self.start,
+            trailer: self.trailer,
+            big_endian: self.big_endian,
+        })
+    }
+}
+fn parse_pattern(text: &str, name: &str) -> Result<Vec<u8>, String> {
+    match parse_hex(text) {
+        Ok(bytes) if !bytes.is_empty() => Ok(bytes),
+        Ok(_) => Err(format!("{}不能为空", name)),
+        Err(e) => Err(format!("{}不是有效的 HEX: {}", name, e)),
+    }
+}
+impl ByteWatcherApp {
+    fn new(ctx: &egui::Context) -> Self {
+        let (events, events_rx) = EventSender::channel(Some(ctx.clone()));
+        tokio::spawn(serial::watch_ports(events.clone()));
         Self {
             connected: false,
             connect_type: ConnectType::SERIAL,
@@ -131,20 +365,108 @@ impl Default for ByteWatcherApp {
                 baud_rate: 115200,
                 data_bits: 8,
                 stop_bits: 1,
+                parity: Parity::None,
+                flow_control: FlowControl::None,
+                serial_number: None,
+                match_serial_number: false,
+                auto_reconnect: false,
             },
-            serial: None,
+            tcp_connect_info: TcpInfo {
+                host: "127.0.0.1".into(),
+                port: 8080,
+            },
+            tcp_server_info: TcpInfo {
+                host: "0.0.0.0".into(),
+                port: 8080,
+            },
+            udp_connect_info: UdpInfo {
+                bind_host: "0.0.0.0".into(),
+                bind_port: 8081,
+                remote: "".into(),
+            },
+            ws_connect_info: WsInfo {
+                url: "ws://127.0.0.1:8080".into(),
+            },
+            transport: None,
+            framing_info: FramingInfo {
+                mode: FramingMode::DELIMITER,
+                delimiter: "55 AA".into(),
+                position: DelimiterPosition::Header,
+                fixed_len: 16,
+                idle_ms: 20,
+                header: "55 AA".into(),
+                length_offset: 2,
+                length_width: 2,
+                length_big_endian: false,
+                length_includes_header: true,
+                length_includes_crc: true,
+                crc_len: 2,
+            },
+            rx_checksum: ChecksumInfo {
+                enabled: false,
+                algorithm: Algorithm::Crc16Xmodem,
+                width: 2,
+                start: 0,
+                trailer: 0,
+                big_endian: false,
+            },
+            tx_checksum: ChecksumInfo {
+                enabled: false,
+                algorithm: Algorithm::Crc16Xmodem,
+                width: 2,
+                start: 0,
+                trailer: 0,
+                big_endian: false,
+            },
+            send_mode: SendMode::HEX,
+            send_text: "".into(),
+            send_target: None,
+            history: History::new(20_000, 64 * 1024 * 1024),
+            ports: Vec::new(),
+            events,
+            events_rx,
+            conn_id: 0,
+            last_error: None,
+            modem_info: ModemInfo {
+                dtr: false,
+                rts: false,
+                pulse_line: Line::Rts,
+                pulse_ms: 100,
+            },
+            modem_lines: None,
+            break_info: BreakInfo {
+                before_send: false,
+                ms: 1,
+            },
+            reconnect: None,
+            display_mode: DisplayMode::Hex,
+            encoding: TextEncoding::Utf8,
+            time_format: TimeFormat::Wall,
+            connected_at: 0,
+            search: Search::new(),
         }
     }
 }
 const LABLE_WIDTH: f32 = 200.0;
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
 impl eframe::App for ByteWatcherApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let mut style = (*ctx.style()).clone();
         style.override_font_id = Some(egui::FontId::new(16.0, FontFamily::Proportional));
         style.spacing.interact_size = egui::Vec2::new(0.0, 30.0); // 影响标签交互区域
         ctx.set_style(style);
-        ctx.request_repaint();
-
+        self.handle_events();
+        if let Some(e) = self.history.take_error() {
+            self.last_error = Some(e);
+        }
+        self.try_reconnect(ctx);
+        self.search.update(&self.history, self.encoding);
+        if self.search.pending(&self.history) {
+            ctx.request_repaint();
+        }
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            gen_status_bar_ui(ui, self);
+        });
         egui::CentralPanel::default().show(ctx, |ui| {
             // let left_width = ui.available_width() * 0.3;
             egui::SidePanel::left("left_panel")
@@ -161,6 +483,7 @@ impl eframe::App for ByteWatcherApp {
                         let connect_type = match self.connect_type {
                             ConnectType::SERIAL => "串口通讯",
                             ConnectType::TCP => "TCP client",
+                            ConnectType::TCPSERVER => "TCP server",
                             ConnectType::UDP => "UDP client",
                             ConnectType::WS => "WS client",
                         };
@@ -176,30 +499,51 @@ impl eframe::App for ByteWatcherApp {
                                         ConnectType::SERIAL,
                                         "串口通讯",
                                     );
-                                    // ui.selectable_value(
-                                    //     &mut self.connect_type,
-                                    //     ConnectType::TCP,
-                                    //     "TCP client",
-                                    // );
-                                    // ui.selectable_value(
-                                    //     &mut self.connect_type,
-                                    //     ConnectType::UDP,
-                                    //     "UDP client",
-                                    // );
-                                    // ui.selectable_value(
-                                    //     &mut self.connect_type,
-                                    //     ConnectType::WS,
-                                    //     "WS client",
-                                    // );
+                                    ui.selectable_value(
+                                        &mut self.connect_type,
+                                        ConnectType::TCP,
+                                        "TCP client",
+                                    );
+                                    ui.selectable_value(
+                                        &mut self.connect_type,
+                                        ConnectType::TCPSERVER,
+                                        "TCP server",
+                                    );
+                                    ui.selectable_value(
+                                        &mut self.connect_type,
+                                        ConnectType::UDP,
+                                        "UDP client",
+                                    );
+                                    ui.selectable_value(
+                                        &mut self.connect_type,
+                                        ConnectType::WS,
+                                        "WS client",
+                                    );
                                 });
                         });
                     });
                     match self.connect_type {
                         ConnectType::SERIAL => gen_serial_config_ui(ui, self),
                         ConnectType::TCP => gen_tcp_config_ui(ui, self),
+                        ConnectType::TCPSERVER => gen_tcp_server_config_ui(ui, self),
                         ConnectType::UDP => gen_udp_config_ui(ui, self),
                         ConnectType::WS => gen_ws_config_ui(ui, self),
                     }
+                    // UDP 和 WebSocket 本身就是按报文收发的，不需要分帧
+                    if matches!(
+                        self.connect_type,
+                        ConnectType::SERIAL | ConnectType::TCP | ConnectType::TCPSERVER
+                    ) {
+                        ui.add_space(10.0);
+                        ui.separator();
+                        gen_framing_config_ui(ui, self);
+                    }
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.checkbox(&mut self.rx_checksum.enabled, "接收校验");
+                    if self.rx_checksum.enabled {
+                        gen_checksum_config_ui(ui, "rx_checksum", &mut self.rx_checksum);
+                    }
                     ui.add_space(10.0);
                     ui.separator();
                     ui.add_space(10.0);
@@ -207,9 +551,20 @@ impl eframe::App for ByteWatcherApp {
                         ui.with_layout(
                             egui::Layout::centered_and_justified(egui::Direction::BottomUp),
                             |ui| {
-                                let (btn_text, btn_color) = match self.connected {
-                                    true => ("断开", egui::Color32::from_rgb(0xC2, 0x18, 0x5B)), // 断开状态显示红色
-                                    false => ("连接", egui::Color32::from_rgb(0x19, 0x76, 0xD2)),
+                                let (btn_text, btn_color) = match (self.connected, self.status()) {
+                                    (true, Status::Connecting) => {
+                                        ("取消连接", egui::Color32::from_rgb(0xF5, 0x7C, 0x00))
+                                    }
+                                    (false, _) if self.reconnect.is_some() => {
+                                        ("取消重连", egui::Color32::from_rgb(0xF5, 0x7C, 0x00))
+                                    }
+                                    // 断开状态显示红色
+                                    (true, _) => {
+                                        ("断开", egui::Color32::from_rgb(0xC2, 0x18, 0x5B))
+                                    }
+                                    (false, _) => {
+                                        ("连接", egui::Color32::from_rgb(0x19, 0x76, 0xD2))
+                                    }
                                 };
                                 if ui
                                     .add_sized(
@@ -223,22 +578,17 @@ impl eframe::App for ByteWatcherApp {
                                     .clicked()
                                 {
                                     if self.connected {
-                                        self.connected = false;
-                                        match self.serial.as_mut() {
-                                            Some(serial) => {
-                                                serial.close();
+                                        self.disconnect();
+                                    } else if self.reconnect.is_some() {
+                                        self.reconnect = None;
+                                    } else {
+                                        match self.connect() {
+                                            Ok(()) => {
+                                                self.connected = true;
+                                                self.last_error = None;
                                             }
-                                            None => {}
+                                            Err(e) => self.last_error = Some(e),
                                         }
-                                    } else {
-                                        let serial = Serial::new(
-                                            &self.serial_connetct_info.path,
-                                            self.serial_connetct_info.baud_rate,
-                                            self.serial_connetct_info.data_bits,
-                                            self.serial_connetct_info.stop_bits,
-                                        );
-                                        self.serial = Some(serial);
-                                        self.connected = true;
                                     }
                                 }
                             },
@@ -251,34 +601,211 @@ impl eframe::App for ByteWatcherApp {
                         });
                     });
                 });
+            egui::TopBottomPanel::bottom("send_panel").show_inside(ui, |ui| {
+                gen_send_ui(ui, self);
+            });
             egui::ScrollArea::vertical().show(ui, |ui| {
                 ui.horizontal(|ui| {
                     ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
                         if ui.button("清理").clicked() {
-                            DATA.lock().unwrap().clear();
+                            self.history.clear();
                         };
+                        ui.menu_button("缓存设置", |ui| {
+                            gen_history_config_ui(ui, &mut self.history)
+                        });
+                        egui::ComboBox::from_id_salt("display_mode")
+                            .selected_text(self.display_mode.name())
+                            .width(100.0)
+                            .show_ui(ui, |ui| {
+                                for mode in DisplayMode::ALL {
+                                    ui.selectable_value(&mut self.display_mode, mode, mode.name());
+                                }
+                            });
+                        if self.display_mode == DisplayMode::Text {
+                            gen_encoding_ui(ui, "display_encoding", &mut self.encoding);
+                        }
+                        egui::ComboBox::from_id_salt("time_format")
+                            .selected_text(self.time_format.name())
+                            .width(100.0)
+                            .show_ui(ui, |ui| {
+                                for format in TimeFormat::ALL {
+                                    ui.selectable_value(
+                                        &mut self.time_format,
+                                        format,
+                                        format.name(),
+                                    );
+                                }
+                            });
                     });
                     ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
                         ui.heading("数据显示")
                             .on_hover_cursor(egui::CursorIcon::Default);
                     });
                 });
-                egui::ScrollArea::vertical()
-                    .auto_shrink(false)
-                    .stick_to_bottom(true)
-                    .show(ui, |ui| {
-                        ui.spacing_mut().item_spacing.y = 5.0;
-                        ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Wrap);
-
-                        let data = DATA.lock().unwrap().clone();
-                        data.into_iter().for_each(|text| {
-                            ui.code(text);
-                        });
-                    });
+                gen_search_ui(ui, &mut self.search, &self.history);
+                gen_data_view_ui(ui, self);
             });
         });
     }
 }
+fn gen_status_bar_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
+    ui.horizontal(|ui| {
+        let status = match bw.status() {
+            Status::Connecting => "连接中",
+            Status::Connected => "已连接",
+            Status::Closed if bw.reconnect.is_some() => "等待重连",
+            Status::Closed => "未连接",
+        };
+        ui.label(status).on_hover_cursor(egui::CursorIcon::Default);
+        if let Some(e) = bw.last_error.as_ref() {
+            ui.separator();
+            ui.colored_label(egui::Color32::RED, e);
+            if ui.small_button("清除").clicked() {
+                bw.last_error = None;
+            }
+        }
+    });
+}
+/// 文本显示和文本发送共用同一个编码设置
+fn gen_encoding_ui(ui: &mut egui::Ui, id: &str, encoding: &mut TextEncoding) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(encoding.name())
+        .width(120.0)
+        .show_ui(ui, |ui| {
+            for item in TextEncoding::ALL {
+                ui.selectable_value(encoding, item, item.name());
+            }
+        });
+}
+fn gen_search_ui(ui: &mut egui::Ui, search: &mut Search, history: &History) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("search_kind")
+            .selected_text(search.kind.name())
+            .width(80.0)
+            .show_ui(ui, |ui| {
+                for kind in SearchKind::ALL {
+                    ui.selectable_value(&mut search.kind, kind, kind.name());
+                }
+            });
+        let hint = match search.kind {
+            SearchKind::Hex => "55 AA ?? 01",
+            SearchKind::Ascii => "ASCII 子串",
+            SearchKind::Regex => "正则表达式",
+        };
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut search.text)
+                .hint_text(hint)
+                .desired_width(240.0),
+        );
+        // 回车跳到下一个，Shift+回车跳到上一个
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            search.jump(!ui.input(|i| i.modifiers.shift));
+            response.request_focus();
+        }
+        if ui.button("上一个").clicked() {
+            search.jump(false);
+        }
+        if ui.button("下一个").clicked() {
+            search.jump(true);
+        }
+        ui.checkbox(&mut search.filter, "只显示匹配");
+        if let Some(e) = search.error.as_ref() {
+            ui.colored_label(egui::Color32::RED, e);
+        } else if search.active() {
+            let current = search.current().map_or(0, |i| i + 1);
+            ui.label(format!("{}/{}", current, search.match_count()));
+            if search.pending(history) {
+                ui.spinner();
+            }
+        }
+    });
+}
+/// 只渲染可见的行，每行固定高度，不换行
+fn gen_data_view_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
+    let font_id = egui::FontId::monospace(14.0);
+    let row_height = ui.fonts(|f| f.row_height(&font_id));
+    let dropped = bw.history.dropped();
+    let filter = bw.search.filter && bw.search.active();
+    let total_rows = match filter {
+        true => bw.search.match_count(),
+        false => bw.history.len(),
+    };
+    ui.spacing_mut().item_spacing.y = 5.0;
+    // 贴底时 ScrollArea 会把偏移改回底部，跳转的这一帧不能贴底
+    let target = bw.search.take_scroll();
+    let mut scroll = egui::ScrollArea::both()
+        .auto_shrink(false)
+        .stick_to_bottom(target.is_none());
+    if let Some(target) = target {
+        let row = if filter {
+            target
+        } else {
+            target.saturating_sub(dropped)
+        };
+        scroll = scroll.vertical_scroll_offset(row as f32 * (row_height + 5.0));
+    }
+    let code_bg = ui.visuals().code_bg_color;
+    let match_bg = egui::Color32::from_rgba_unmultiplied(255, 200, 0, 90);
+    let current_bg = ui.visuals().selection.bg_fill;
+    scroll.show_rows(ui, row_height, total_rows, |ui, range| {
+        ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
+        for row in range {
+            let seq = match filter {
+                true => bw.search.matched(row),
+                false => Some(dropped + row),
+            };
+            let Some(index) = seq.and_then(|seq| seq.checked_sub(dropped)) else {
+                continue;
+            };
+            let Some(record) = bw.history.get(index) else {
+                continue;
+            };
+            // 帧间隔按历史记录中上一条收发数据计算，不管它有没有被过滤掉
+            let prev = match bw.time_format {
+                TimeFormat::Delta => bw.history.prev_frame(index),
+                _ => None,
+            };
+            let highlight_bg = match bw.search.is_current(dropped + index) {
+                true => current_bg,
+                false => match_bg,
+            };
+            let color = match record.status {
+                FrameStatus::Bad => egui::Color32::RED,
+                _ => ui.visuals().text_color(),
+            };
+            let mut job = egui::text::LayoutJob::default();
+            for (text, highlight) in bw.format_record(&record, prev.as_deref()) {
+                let format = egui::TextFormat {
+                    font_id: font_id.clone(),
+                    color,
+                    background: if highlight { highlight_bg } else { code_bg },
+                    ..Default::default()
+                };
+                job.append(&text, 0.0, format);
+            }
+            ui.label(job);
+        }
+    });
+    // 读磁盘的错误在下一帧显示到状态栏
+    if bw.history.has_error() {
+        ui.ctx().request_repaint();
+    }
+}
+fn gen_history_config_ui(ui: &mut egui::Ui, data: &mut History) {
+    let mut max_mb = data.max_bytes / 1024 / 1024;
+    egui::Grid::new("history_config").show(ui, |ui| {
+        ui.label("最多记录条数");
+        ui.add(egui::DragValue::new(&mut data.max_records).range(1..=10_000_000));
+        ui.end_row();
+        ui.label("内存上限(MB)");
+        ui.add(egui::DragValue::new(&mut max_mb).range(1..=4096));
+        ui.end_row();
+    });
+    ui.checkbox(&mut data.spill, "超出部分写入磁盘")
+        .on_hover_text("关闭后已写入磁盘的记录会被删除");
+    data.max_bytes = max_mb * 1024 * 1024;
+    data.evict();
+}
 fn gen_serial_config_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
     ui.horizontal(|ui| {
         ui.set_width(LABLE_WIDTH);
@@ -290,12 +817,37 @@ fn gen_serial_config_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
                 .selected_text(bw.serial_connetct_info.path.as_str())
                 .width(100.0)
                 .show_ui(ui, |ui| {
-                    SERIALS.lock().unwrap().iter().for_each(|(k, v)| {
-                        ui.selectable_value(&mut bw.serial_connetct_info.path, k.into(), v);
-                    });
+                    ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
+                    let info = &mut bw.serial_connetct_info;
+                    for port in bw.ports.iter() {
+                        let selected = info.path == port.port_name;
+                        if ui.selectable_label(selected, port_label(port)).clicked() {
+                            info.path = port.port_name.clone();
+                            info.serial_number = usb_serial_number(port).map(String::from);
+                        }
+                    }
                 });
         });
     });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        let info = &mut bw.serial_connetct_info;
+        let res = ui.add_enabled(
+            info.serial_number.is_some(),
+            egui::Checkbox::new(&mut info.match_serial_number, "按 USB 序列号识别"),
+        );
+        if let Some(serial_number) = info.serial_number.as_ref() {
+            res.on_hover_text(format!(
+                "串口号变化时自动切换到序列号为 {} 的设备",
+                serial_number
+            ));
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.checkbox(&mut bw.serial_connetct_info.auto_reconnect, "断线自动重连")
+            .on_hover_text("设备重新出现时按串口号或 USB 序列号重新打开");
+    });
     ui.horizontal(|ui| {
         ui.set_width(LABLE_WIDTH);
         ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
@@ -336,25 +888,882 @@ fn gen_serial_config_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
             .on_hover_cursor(egui::CursorIcon::Text);
         });
     });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        let parity = match bw.serial_connetct_info.parity {
+            Parity::None => "无",
+            Parity::Odd => "奇校验",
+            Parity::Even => "偶校验",
+        };
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("校验位");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            egui::ComboBox::from_id_salt("parity")
+                .selected_text(parity)
+                .width(100.0)
+                .show_ui(ui, |ui| {
+                    let info = &mut bw.serial_connetct_info;
+                    ui.selectable_value(&mut info.parity, Parity::None, "无");
+                    ui.selectable_value(&mut info.parity, Parity::Odd, "奇校验");
+                    ui.selectable_value(&mut info.parity, Parity::Even, "偶校验");
+                })
+                .response
+                .on_hover_text("不支持 Mark/Space 校验");
+        });
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        let flow_control = match bw.serial_connetct_info.flow_control {
+            FlowControl::None => "无",
+            FlowControl::Software => "XON/XOFF",
+            FlowControl::Hardware => "RTS/CTS",
+        };
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("流控");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            egui::ComboBox::from_id_salt("flow_control")
+                .selected_text(flow_control)
+                .width(100.0)
+                .show_ui(ui, |ui| {
+                    let info = &mut bw.serial_connetct_info;
+                    ui.selectable_value(&mut info.flow_control, FlowControl::None, "无");
+                    ui.selectable_value(&mut info.flow_control, FlowControl::Software, "XON/XOFF");
+                    ui.selectable_value(&mut info.flow_control, FlowControl::Hardware, "RTS/CTS");
+                });
+        });
+    });
+    gen_modem_ui(ui, bw);
+}
+fn gen_modem_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
+    let serial = bw.transport.as_ref().and_then(|t| t.as_serial());
+    let info = &mut bw.modem_info;
+    let mut result = Ok(());
+    ui.add_space(10.0);
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.label("控制线");
+        if ui.checkbox(&mut info.dtr, "DTR").changed() {
+            if let Some(serial) = serial {
+                result = serial.control(Control::Set(Line::Dtr, info.dtr));
+            }
+        }
+        if ui.checkbox(&mut info.rts, "RTS").changed() {
+            if let Some(serial) = serial {
+                result = serial.control(Control::Set(Line::Rts, info.rts));
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        let line = match info.pulse_line {
+            Line::Dtr => "DTR",
+            Line::Rts => "RTS",
+        };
+        egui::ComboBox::from_id_salt("pulse_line")
+            .selected_text(line)
+            .width(60.0)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut info.pulse_line, Line::Dtr, "DTR");
+                ui.selectable_value(&mut info.pulse_line, Line::Rts, "RTS");
+            });
+        ui.add(
+            egui::DragValue::new(&mut info.pulse_ms)
+                .range(1..=10_000)
+                .suffix(" ms"),
+        );
+        if ui
+            .add_enabled(serial.is_some(), egui::Button::new("复位"))
+            .on_hover_text("控制线翻转成相反电平，保持设定时间后恢复")
+            .clicked()
+        {
+            if let Some(serial) = serial {
+                let duration = Duration::from_millis(info.pulse_ms);
+                result = serial.control(Control::Pulse(info.pulse_line, duration));
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.label("状态线");
+        let lines = bw.modem_lines;
+        for (name, level) in [
+            ("CTS", lines.map(|l| l.cts)),
+            ("DSR", lines.map(|l| l.dsr)),
+            ("CD", lines.map(|l| l.cd)),
+            ("RI", lines.map(|l| l.ri)),
+        ] {
+            let color = match level {
+                Some(true) => egui::Color32::from_rgb(0x2E, 0x7D, 0x32),
+                Some(false) => egui::Color32::GRAY,
+                None => egui::Color32::DARK_GRAY,
+            };
+            ui.colored_label(color, name)
+                .on_hover_cursor(egui::CursorIcon::Default);
+        }
+    });
+    if let Err(e) = result {
+        bw.last_error = Some(e);
+    }
 }
 fn gen_tcp_config_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
     ui.horizontal(|ui| {
-        ui.label("通讯类型11")
-            .on_hover_cursor(egui::CursorIcon::Default);
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("地址");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_sized(
+                [100.0, 20.0],
+                egui::TextEdit::singleline(&mut bw.tcp_connect_info.host),
+            );
+        });
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("端口");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_sized(
+                [100.0, 20.0],
+                egui::DragValue::new(&mut bw.tcp_connect_info.port).speed(0),
+            )
+            .on_hover_cursor(egui::CursorIcon::Text);
+        });
+    });
+}
+fn gen_tcp_server_config_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("监听地址");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_sized(
+                [100.0, 20.0],
+                egui::TextEdit::singleline(&mut bw.tcp_server_info.host),
+            );
+        });
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("监听端口");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_sized(
+                [100.0, 20.0],
+                egui::DragValue::new(&mut bw.tcp_server_info.port).speed(0),
+            )
+            .on_hover_cursor(egui::CursorIcon::Text);
+        });
     });
+    if let Some(clients) = bw.transport.as_ref().and_then(|t| t.clients()) {
+        ui.add_space(10.0);
+        ui.label("已连接客户端");
+        for addr in clients {
+            ui.label(addr.to_string());
+        }
+    }
 }
 fn gen_udp_config_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
     ui.horizontal(|ui| {
-        ui.label("通讯类型12")
-            .on_hover_cursor(egui::CursorIcon::Default);
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("本地地址");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_sized(
+                [100.0, 20.0],
+                egui::TextEdit::singleline(&mut bw.udp_connect_info.bind_host),
+            );
+        });
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("本地端口");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_sized(
+                [100.0, 20.0],
+                egui::DragValue::new(&mut bw.udp_connect_info.bind_port).speed(0),
+            )
+            .on_hover_cursor(egui::CursorIcon::Text);
+        });
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("远端地址");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_sized(
+                [100.0, 20.0],
+                egui::TextEdit::singleline(&mut bw.udp_connect_info.remote)
+                    .hint_text("可选 host:port"),
+            );
+        });
     });
 }
 fn gen_ws_config_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
     ui.horizontal(|ui| {
-        ui.label("通讯类型133")
-            .on_hover_cursor(egui::CursorIcon::Default);
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("URL");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_sized(
+                [150.0, 20.0],
+                egui::TextEdit::singleline(&mut bw.ws_connect_info.url),
+            );
+        });
+    });
+}
+fn gen_framing_config_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
+    let info = &mut bw.framing_info;
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        let mode = match info.mode {
+            FramingMode::RAW => "原始数据",
+            FramingMode::DELIMITER => "分隔符",
+            FramingMode::FIXED => "固定长度",
+            FramingMode::IDLE => "空闲超时",
+            FramingMode::LENGTH => "帧头+长度",
+        };
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("分帧方式");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            egui::ComboBox::from_id_salt("framing")
+                .selected_text(mode)
+                .width(100.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut info.mode, FramingMode::RAW, "原始数据");
+                    ui.selectable_value(&mut info.mode, FramingMode::DELIMITER, "分隔符");
+                    ui.selectable_value(&mut info.mode, FramingMode::FIXED, "固定长度");
+                    ui.selectable_value(&mut info.mode, FramingMode::IDLE, "空闲超时");
+                    ui.selectable_value(&mut info.mode, FramingMode::LENGTH, "帧头+长度");
+                });
+        });
+    });
+    match info.mode {
+        FramingMode::RAW => {}
+        FramingMode::DELIMITER => {
+            ui.horizontal(|ui| {
+                ui.set_width(LABLE_WIDTH);
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    ui.label("分隔符");
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add_sized(
+                        [100.0, 20.0],
+                        egui::TextEdit::singleline(&mut info.delimiter).hint_text("0D 0A"),
+                    );
+                });
+            });
+            ui.horizontal(|ui| {
+                ui.set_width(LABLE_WIDTH);
+                let position = match info.position {
+                    DelimiterPosition::Header => "帧头",
+                    DelimiterPosition::Trailer => "帧尾",
+                };
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    ui.label("位置");
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    egui::ComboBox::from_id_salt("delimiter_position")
+                        .selected_text(position)
+                        .width(100.0)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut info.position,
+                                DelimiterPosition::Header,
+                                "帧头",
+                            );
+                            ui.selectable_value(
+                                &mut info.position,
+                                DelimiterPosition::Trailer,
+                                "帧尾",
+                            );
+                        });
+                });
+            });
+        }
+        FramingMode::FIXED => {
+            ui.horizontal(|ui| {
+                ui.set_width(LABLE_WIDTH);
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    ui.label("帧长度");
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add_sized(
+                        [100.0, 20.0],
+                        egui::DragValue::new(&mut info.fixed_len).speed(0),
+                    )
+                    .on_hover_cursor(egui::CursorIcon::Text);
+                });
+            });
+        }
+        FramingMode::IDLE => {
+            ui.horizontal(|ui| {
+                ui.set_width(LABLE_WIDTH);
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    ui.label("超时(ms)");
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add_sized(
+                        [100.0, 20.0],
+                        egui::DragValue::new(&mut info.idle_ms).speed(0),
+                    )
+                    .on_hover_cursor(egui::CursorIcon::Text);
+                });
+            });
+        }
+        FramingMode::LENGTH => {
+            ui.horizontal(|ui| {
+                ui.set_width(LABLE_WIDTH);
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    ui.label("帧头");
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add_sized(
+                        [100.0, 20.0],
+                        egui::TextEdit::singleline(&mut info.header).hint_text("55 AA"),
+                    );
+                });
+            });
+            ui.horizontal(|ui| {
+                ui.set_width(LABLE_WIDTH);
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    ui.label("长度偏移");
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add_sized(
+                        [100.0, 20.0],
+                        egui::DragValue::new(&mut info.length_offset).speed(0),
+                    )
+                    .on_hover_cursor(egui::CursorIcon::Text);
+                });
+            });
+            ui.horizontal(|ui| {
+                ui.set_width(LABLE_WIDTH);
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    ui.label("长度字节数");
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    egui::ComboBox::from_id_salt("length_width")
+                        .selected_text(info.length_width.to_string())
+                        .width(100.0)
+                        .show_ui(ui, |ui| {
+                            for width in [1, 2, 4] {
+                                ui.selectable_value(
+                                    &mut info.length_width,
+                                    width,
+                                    width.to_string(),
+                                );
+                            }
+                        });
+                });
+            });
+            ui.horizontal(|ui| {
+                ui.set_width(LABLE_WIDTH);
+                let endian = match info.length_big_endian {
+                    true => "大端",
+                    false => "小端",
+                };
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    ui.label("字节序");
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    egui::ComboBox::from_id_salt("length_endian")
+                        .selected_text(endian)
+                        .width(100.0)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut info.length_big_endian, false, "小端");
+                            ui.selectable_value(&mut info.length_big_endian, true, "大端");
+                        });
+                });
+            });
+            ui.checkbox(&mut info.length_includes_header, "长度包含帧头和长度字段");
+            ui.checkbox(&mut info.length_includes_crc, "长度包含校验");
+            ui.horizontal(|ui| {
+                ui.set_width(LABLE_WIDTH);
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    ui.label("校验字节数");
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add_enabled(
+                        !info.length_includes_crc,
+                        egui::DragValue::new(&mut info.crc_len).speed(0),
+                    )
+                    .on_hover_cursor(egui::CursorIcon::Text);
+                });
+            });
+        }
+    }
+}
+fn gen_checksum_config_ui(ui: &mut egui::Ui, id: &str, info: &mut ChecksumInfo) {
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("校验算法");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            egui::ComboBox::from_id_salt((id, "algorithm"))
+                .selected_text(info.algorithm.name())
+                .width(100.0)
+                .show_ui(ui, |ui| {
+                    for algorithm in Algorithm::ALL {
+                        if ui
+                            .selectable_value(&mut info.algorithm, algorithm, algorithm.name())
+                            .clicked()
+                        {
+                            info.width = algorithm.width();
+                        }
+                    }
+                });
+        });
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("校验字节数");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            gen_checksum_width_ui(ui, id, info);
+        });
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("起始字节");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_sized(
+                [100.0, 20.0],
+                egui::DragValue::new(&mut info.start).speed(0),
+            )
+            .on_hover_text("从第几个字节开始参与计算，0 表示帧首")
+            .on_hover_cursor(egui::CursorIcon::Text);
+        });
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("校验后字节");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.add_sized(
+                [100.0, 20.0],
+                egui::DragValue::new(&mut info.trailer).speed(0),
+            )
+            .on_hover_text("校验值之后还有几个字节，比如帧尾")
+            .on_hover_cursor(egui::CursorIcon::Text);
+        });
+    });
+    ui.horizontal(|ui| {
+        ui.set_width(LABLE_WIDTH);
+        let endian = match info.big_endian {
+            true => "大端",
+            false => "小端",
+        };
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+            ui.label("字节序");
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            egui::ComboBox::from_id_salt((id, "endian"))
+                .selected_text(endian)
+                .width(100.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut info.big_endian, false, "小端");
+                    ui.selectable_value(&mut info.big_endian, true, "大端");
+                });
+        });
+    });
+}
+fn gen_checksum_width_ui(ui: &mut egui::Ui, id: &str, info: &mut ChecksumInfo) {
+    egui::ComboBox::from_id_salt((id, "width"))
+        .selected_text(info.width.to_string())
+        .width(60.0)
+        .show_ui(ui, |ui| {
+            for width in [1, 2, 4] {
+                ui.selectable_value(&mut info.width, width, width.to_string());
+            }
+        });
+}
+/// 发送区一行放下的校验设置
+fn gen_tx_checksum_ui(ui: &mut egui::Ui, info: &mut ChecksumInfo) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut info.enabled, "追加校验");
+        ui.add_enabled_ui(info.enabled, |ui| {
+            egui::ComboBox::from_id_salt(("tx_checksum", "algorithm"))
+                .selected_text(info.algorithm.name())
+                .width(120.0)
+                .show_ui(ui, |ui| {
+                    for algorithm in Algorithm::ALL {
+                        if ui
+                            .selectable_value(&mut info.algorithm, algorithm, algorithm.name())
+                            .clicked()
+                        {
+                            info.width = algorithm.width();
+                        }
+                    }
+                });
+            ui.label("字节数");
+            gen_checksum_width_ui(ui, "tx_checksum", info);
+            ui.label("起始");
+            ui.add(egui::DragValue::new(&mut info.start).speed(0))
+                .on_hover_text("从第几个字节开始参与计算，0 表示帧首");
+            ui.label("校验后");
+            ui.add(egui::DragValue::new(&mut info.trailer).speed(0))
+                .on_hover_text("校验值插在倒数第几个字节之前，比如帧尾");
+            let endian = match info.big_endian {
+                true => "大端",
+                false => "小端",
+            };
+            egui::ComboBox::from_id_salt(("tx_checksum", "endian"))
+                .selected_text(endian)
+                .width(60.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut info.big_endian, false, "小端");
+                    ui.selectable_value(&mut info.big_endian, true, "大端");
+                });
+        });
     });
 }
+fn gen_send_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp) {
+    let connected = bw.status() == Status::Connected;
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        let send_mode = match bw.send_mode {
+            SendMode::HEX => "HEX",
+            SendMode::TEXT => "文本",
+        };
+        egui::ComboBox::from_id_salt("send_mode")
+            .selected_text(send_mode)
+            .width(60.0)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut bw.send_mode, SendMode::HEX, "HEX");
+                ui.selectable_value(&mut bw.send_mode, SendMode::TEXT, "文本");
+            });
+        if bw.send_mode == SendMode::TEXT {
+            gen_encoding_ui(ui, "send_encoding", &mut bw.encoding);
+        }
+        if let Some(clients) = bw.transport.as_ref().and_then(|t| t.clients()) {
+            if bw
+                .send_target
+                .is_some_and(|target| !clients.contains(&target))
+            {
+                bw.send_target = None;
+            }
+            let send_target = match bw.send_target {
+                Some(addr) => addr.to_string(),
+                None => "全部客户端".into(),
+            };
+            egui::ComboBox::from_id_salt("send_target")
+                .selected_text(send_target)
+                .width(160.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut bw.send_target, None, "全部客户端");
+                    for addr in clients {
+                        ui.selectable_value(&mut bw.send_target, Some(addr), addr.to_string());
+                    }
+                });
+        }
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            let clicked = ui
+                .add_enabled(connected, egui::Button::new("发送"))
+                .clicked();
+            let hint = match bw.send_mode {
+                SendMode::HEX => "55 AA 1B 00",
+                SendMode::TEXT => "",
+            };
+            let res = ui.add_sized(
+                ui.available_size(),
+                egui::TextEdit::singleline(&mut bw.send_text).hint_text(hint),
+            );
+            let entered = res.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if connected && (clicked || entered) {
+                bw.send();
+            }
+        });
+    });
+    gen_tx_checksum_ui(ui, &mut bw.tx_checksum);
+    if bw.connect_type == ConnectType::SERIAL {
+        gen_break_ui(ui, bw, connected);
+    }
+    if bw.tx_checksum.enabled && !bw.send_text.is_empty() {
+        match bw.build_payload() {
+            Ok(bytes) => ui.code(format!("预览: {}", to_hex(&bytes))),
+            Err(e) => ui.colored_label(egui::Color32::RED, e),
+        };
+    }
+    ui.add_space(5.0);
+}
+/// 串口的 BREAK 可以单独发送，也可以放在每次发送的数据之前，比如 LIN 的 break + 0x55 同步
+fn gen_break_ui(ui: &mut egui::Ui, bw: &mut ByteWatcherApp, connected: bool) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut bw.break_info.before_send, "先发 BREAK");
+        ui.add(
+            egui::DragValue::new(&mut bw.break_info.ms)
+                .range(1..=10_000)
+                .suffix(" ms"),
+        );
+        if ui
+            .add_enabled(connected, egui::Button::new("发送 BREAK"))
+            .clicked()
+        {
+            if let Some(serial) = bw.transport.as_ref().and_then(|t| t.as_serial()) {
+                if let Err(e) = serial.send_break(Duration::from_millis(bw.break_info.ms)) {
+                    bw.last_error = Some(e);
+                }
+            }
+        }
+    });
+}
+impl ByteWatcherApp {
+    fn connect(&mut self) -> Result<(), String> {
+        self.connect_as(self.connect_type)
+    }
+    fn connect_as(&mut self, connect_type: ConnectType) -> Result<(), String> {
+        self.conn_id += 1;
+        self.connected_at = monotonic_micros();
+        let events = self.events.with_conn(self.conn_id);
+        let transport: Box<dyn Transport> = match connect_type {
+            ConnectType::SERIAL => Box::new(Serial::open(
+                self.serial_connetct_info.to_builder()?,
+                self.framing_info.to_codec()?,
+                events,
+            )?),
+            ConnectType::TCP => Box::new(Tcp::open(
+                &self.tcp_connect_info.host,
+                self.tcp_connect_info.port,
+                self.framing_info.to_codec()?,
+                events,
+            )),
+            ConnectType::TCPSERVER => Box::new(TcpServer::open(
+                &self.tcp_server_info.host,
+                self.tcp_server_info.port,
+                self.framing_info.to_codec()?,
+                events,
+            )),
+            ConnectType::UDP => Box::new(Udp::open(
+                &self.udp_connect_info.bind_host,
+                self.udp_connect_info.bind_port,
+                &self.udp_connect_info.remote,
+                events,
+            )),
+            ConnectType::WS => Box::new(Ws::open(&self.ws_connect_info.url, events)),
+        };
+        // 连接后按界面上的设置同步控制线电平
+        if let Some(serial) = transport.as_serial() {
+            let synced = serial
+                .control(Control::Set(Line::Dtr, self.modem_info.dtr))
+                .and_then(|()| serial.control(Control::Set(Line::Rts, self.modem_info.rts)));
+            if let Err(e) = synced {
+                transport.close();
+                return Err(e);
+            }
+        }
+        self.send_target = None;
+        self.transport = Some(transport);
+        Ok(())
+    }
+    fn status(&self) -> Status {
+        self.transport
+            .as_ref()
+            .map_or(Status::Closed, |transport| transport.status())
+    }
+    fn disconnect(&mut self) {
+        self.connected = false;
+        self.modem_lines = None;
+        if let Some(transport) = self.transport.take() {
+            transport.close();
+        }
+    }
+    /// 发送区的内容转成最终要发出去的字节，包括追加的校验
+    fn build_payload(&self) -> Result<Vec<u8>, String> {
+        let bytes = match self.send_mode {
+            SendMode::HEX => {
+                parse_hex(&self.send_text).map_err(|e| format!("HEX 格式错误: {}", e))?
+            }
+            SendMode::TEXT => self.encoding.encode(&self.send_text)?,
+        };
+        match self.tx_checksum.to_spec() {
+            Some(spec) => spec
+                .append(&bytes)
+                .ok_or_else(|| "数据长度不足，无法计算校验".to_string()),
+            None => Ok(bytes),
+        }
+    }
+    fn send(&mut self) {
+        let bytes = match self.build_payload() {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                self.last_error = Some(e);
+                return;
+            }
+        };
+        if let Some(transport) = self.transport.as_ref() {
+            let mut frame = Outgoing::new(bytes);
+            frame.target = self.send_target;
+            if self.break_info.before_send {
+                frame.break_before = Some(Duration::from_millis(self.break_info.ms));
+            }
+            // UTF-8 纯文本在 WebSocket 上发 Text 帧，其余发 Binary 帧
+            frame.text = self.send_mode == SendMode::TEXT
+                && self.encoding == TextEncoding::Utf8
+                && !self.tx_checksum.enabled;
+            if let Err(e) = transport.write(frame) {
+                self.last_error = Some(e);
+            }
+        }
+    }
+    /// 连接意外断开，串口开启了自动重连时等设备重新出现
+    fn connection_lost(&mut self) {
+        let was_serial = self
+            .transport
+            .as_ref()
+            .is_some_and(|t| t.as_serial().is_some());
+        self.disconnect();
+        let info = &self.serial_connetct_info;
+        self.reconnect = Reconnect::after_loss(was_serial, info, Instant::now());
+        // 断开前通常已经收到了具体的错误，保留它只补充重连的提示
+        self.last_error = Some(match (self.last_error.take(), &self.reconnect) {
+            (Some(e), Some(_)) => format!("{}，等待重连", e),
+            (Some(e), None) => e,
+            (None, Some(_)) => format!("{} 已断开，等待重连", info.path),
+            (None, None) => "连接已断开".to_string(),
+        });
+    }
+    /// 串口列表中出现同一设备时重新打开，每秒最多尝试一次
+    fn try_reconnect(&mut self, ctx: &egui::Context) {
+        let Some(reconnect) = self.reconnect.as_mut() else {
+            return;
+        };
+        let now = Instant::now();
+        match reconnect.check(&self.serial_connetct_info, &self.ports, now) {
+            ReconnectAction::Cancel => {
+                self.reconnect = None;
+                self.last_error = Some("连接已断开".to_string());
+                return;
+            }
+            ReconnectAction::Wait => {
+                ctx.request_repaint_after(RECONNECT_INTERVAL);
+                return;
+            }
+            ReconnectAction::Attempt => ctx.request_repaint_after(RECONNECT_INTERVAL),
+        }
+        reconnect.attempt_at = Some(now);
+        let lost_at = reconnect.lost_at;
+        // 等待期间切换了连接方式也只重连串口
+        match self.connect_as(ConnectType::SERIAL) {
+            Ok(()) => {
+                self.connected = true;
+                self.reconnect = None;
+                self.last_error = None;
+                let gap = format!(
+                    "重新连接，中断 {:.1} 秒，期间的数据已丢失",
+                    lost_at.elapsed().as_secs_f32()
+                );
+                let record = Record::message(&gap).with_conn(self.conn_id);
+                self.history.push(record.with_tag("GAP"));
+            }
+            Err(e) => self.last_error = Some(e),
+        }
+    }
+    /// 一行显示的内容，搜索匹配的字节标记为高亮
+    fn format_record(&self, record: &Record, prev: Option<&Record>) -> Vec<(String, bool)> {
+        let mut line = self.time_format.format(record, prev, self.connected_at);
+        if record.direction == Direction::Tx {
+            line.push_str("--TX");
+        }
+        for label in [&record.source, &record.tag].into_iter().flatten() {
+            line.push_str("--");
+            line.push_str(label);
+        }
+        line.push_str("--");
+        let ranges = self.search.find(record);
+        match record.direction {
+            Direction::Info => line.push_str(&String::from_utf8_lossy(&record.bytes)),
+            _ if ranges.is_empty() => {
+                line.push_str(&self.display_mode.format(&record.bytes, self.encoding))
+            }
+            Direction::Rx | Direction::Tx => {
+                let mut segments = vec![(line, false)];
+                segments.extend(
+                    self.display_mode
+                        .highlight(&record.bytes, self.encoding, &ranges),
+                );
+                return segments;
+            }
+        }
+        vec![(line, false)]
+    }
+    /// 处理通讯任务发来的事件，发送的数据由任务写出成功后回显
+    fn handle_events(&mut self) {
+        while let Ok((conn, event)) = self.events_rx.try_recv() {
+            match event {
+                Event::RxFrame(mut record) => {
+                    if let Some(spec) = self.rx_checksum.to_spec() {
+                        record.status = spec.verify(&record.bytes);
+                    }
+                    self.history.push(record);
+                }
+                Event::TxFrame(record) => self.history.push(record),
+                Event::Connected(source) => {
+                    let record = Record::message("已连接").with_conn(conn);
+                    self.history.push(with_source(record, source));
+                }
+                Event::Disconnected(source) => {
+                    let main = source.is_none();
+                    let record = Record::message("已断开").with_conn(conn);
+                    self.history.push(with_source(record, source));
+                    // 对端关闭或读取出错时任务已经退出，界面同步回到未连接
+                    if main && conn == self.conn_id && self.connected {
+                        self.connection_lost();
+                    }
+                }
+                Event::Error(e) => {
+                    self.history.push(Record::message(&e).with_conn(conn));
+                    self.last_error = Some(e);
+                }
+                Event::PortListChanged(ports) => {
+                    // 有的驱动拔掉设备后读不到错误，串口从列表中消失也当作断开
+                    let lost = self
+                        .transport
+                        .as_ref()
+                        .is_some_and(|t| t.as_serial().is_some())
+                        && self.serial_connetct_info.lost(&ports);
+                    self.serial_connetct_info.resolve(&ports);
+                    self.ports = ports;
+                    if lost && self.connected {
+                        self.connection_lost();
+                    }
+                }
+                Event::ModemChanged(lines) => {
+                    if conn == self.conn_id {
+                        self.modem_lines = Some(lines);
+                    }
+                }
+            }
+        }
+    }
+}
+fn with_source(record: Record, source: Option<String>) -> Record {
+    match source {
+        Some(source) => record.with_source(source),
+        None => record,
+    }
+}
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, hex::FromHexError> {
+    let digits: String = text.split_whitespace().collect();
+    hex::decode(digits)
+}
 #[test]
 fn test_crc() {
     // let state: crc16::State<crc16::XMODEM> = crc16::State::new();
@@ -369,3 +1778,124 @@ fn test_crc() {
     println!("sum {}", hex::encode(sum.to_le_bytes()))
     
 }
+#[test]
+fn test_parse_hex() {
+    assert_eq!(
+        parse_hex("55 AA 1B 00").unwrap(),
+        vec![0x55, 0xaa, 0x1b, 0x00]
+    );
+    assert_eq!(
+        parse_hex("55aa\n1b00").unwrap(),
+        vec![0x55, 0xaa, 0x1b, 0x00]
+    );
+    assert!(parse_hex("55 A").is_err());
+}
+
+#[cfg(test)]
+fn serial_info(path: &str) -> SerialInfo {
+    SerialInfo {
+        path: path.into(),
+        baud_rate: 9600,
+        data_bits: 8,
+        stop_bits: 1,
+        parity: Parity::None,
+        flow_control: FlowControl::None,
+        serial_number: None,
+        match_serial_number: false,
+        auto_reconnect: false,
+    }
+}
+
+#[test]
+fn test_serial_builder() {
+    let mut info = SerialInfo {
+        parity: Parity::Even,
+        flow_control: FlowControl::Hardware,
+        ..serial_info("")
+    };
+    assert_eq!(info.to_builder().err().as_deref(), Some("请选择串口"));
+    info.path = "COM1".into();
+    info.data_bits = 9;
+    assert_eq!(
+        info.to_builder().err().as_deref(),
+        Some("不支持的数据位: 9")
+    );
+    info.data_bits = 8;
+    info.stop_bits = 7;
+    assert_eq!(
+        info.to_builder().err().as_deref(),
+        Some("不支持的停止位: 7")
+    );
+    info.stop_bits = 1;
+    let builder = info.to_builder().unwrap();
+    let expected = tokio_serial::new("COM1", 9600)
+        .parity(Parity::Even)
+        .flow_control(FlowControl::Hardware);
+    assert_eq!(builder, expected);
+}
+
+#[test]
+fn test_resolve_serial_number() {
+    let usb = |name: &str, serial_number: &str| SerialPortInfo {
+        port_name: name.into(),
+        port_type: tokio_serial::SerialPortType::UsbPort(tokio_serial::UsbPortInfo {
+            vid: 0x0403,
+            pid: 0x6001,
+            serial_number: Some(serial_number.into()),
+            manufacturer: Some("FTDI".into()),
+            product: None,
+        }),
+    };
+    let mut info = SerialInfo {
+        serial_number: Some("A1".into()),
+        ..serial_info("/dev/ttyUSB0")
+    };
+    let ports = [usb("/dev/ttyUSB0", "B2"), usb("/dev/ttyUSB1", "A1")];
+    info.resolve(&ports);
+    assert_eq!(info.path, "/dev/ttyUSB0");
+    info.match_serial_number = true;
+    info.resolve(&ports);
+    assert_eq!(info.path, "/dev/ttyUSB1");
+    // 设备拔掉时保留原来的串口号
+    info.resolve(&ports[..1]);
+    assert_eq!(info.path, "/dev/ttyUSB1");
+}
+
+#[test]
+fn test_reconnect_decisions() {
+    let mut info = serial_info("/dev/ttyUSB0");
+    let port = |name: &str| SerialPortInfo {
+        port_name: name.into(),
+        port_type: tokio_serial::SerialPortType::Unknown,
+    };
+    let now = Instant::now();
+    // 没开自动重连或断开的不是串口时不等待
+    assert!(Reconnect::after_loss(true, &info, now).is_none());
+    info.auto_reconnect = true;
+    assert!(Reconnect::after_loss(false, &info, now).is_none());
+    let mut reconnect = Reconnect::after_loss(true, &info, now).unwrap();
+    assert!(info.lost(&[port("/dev/ttyUSB1")]));
+    assert_eq!(
+        reconnect.check(&info, &[port("/dev/ttyUSB1")], now),
+        ReconnectAction::Wait
+    );
+    let ports = [port("/dev/ttyUSB0")];
+    assert!(!info.lost(&ports));
+    assert_eq!(
+        reconnect.check(&info, &ports, now),
+        ReconnectAction::Attempt
+    );
+    // 失败后隔一段时间再试
+    reconnect.attempt_at = Some(now);
+    assert_eq!(reconnect.check(&info, &ports, now), ReconnectAction::Wait);
+    let later = now + RECONNECT_INTERVAL;
+    assert_eq!(
+        reconnect.check(&info, &ports, later),
+        ReconnectAction::Attempt
+    );
+    info.auto_reconnect = false;
+    assert_eq!(
+        reconnect.check(&info, &ports, later),
+        ReconnectAction::Cancel
+    );
+}
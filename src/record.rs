@@ -0,0 +1,86 @@
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime};
+
+use crate::checksum::FrameStatus;
+
+/// 进程内单调时钟的起点
+static CLOCK_START: OnceLock<Instant> = OnceLock::new();
+
+pub fn monotonic_micros() -> u64 {
+    CLOCK_START.get_or_init(Instant::now).elapsed().as_micros() as u64
+}
+
+/// 数据到达的时间，分帧输出比收到数据晚时用它保留实际的到达时间
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Timestamp {
+    pub time: SystemTime,
+    pub mono: u64,
+}
+impl Timestamp {
+    pub fn now() -> Timestamp {
+        Timestamp {
+            time: SystemTime::now(),
+            mono: monotonic_micros(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    Rx,
+    Tx,
+    /// 连接、断开之类的提示信息，`bytes` 为 UTF-8 文本
+    Info,
+}
+
+/// 数据区的一条记录，只保存原始字节，显示时再格式化
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub bytes: Vec<u8>,
+    pub direction: Direction,
+    pub time: SystemTime,
+    /// 单调时钟的微秒数，计算帧间隔时不受系统时间调整影响
+    pub mono: u64,
+    /// 产生这条记录的连接编号，每次连接递增，0 表示不属于任何连接
+    pub conn: usize,
+    /// 来源连接，比如 TCP server 的客户端地址、UDP 对端地址
+    pub source: Option<String>,
+    /// 附加标记，比如 WebSocket 的帧类型
+    pub tag: Option<String>,
+    pub status: FrameStatus,
+}
+impl Record {
+    pub fn new(direction: Direction, bytes: Vec<u8>) -> Record {
+        let now = Timestamp::now();
+        Record {
+            bytes,
+            direction,
+            time: now.time,
+            mono: now.mono,
+            conn: 0,
+            source: None,
+            tag: None,
+            status: FrameStatus::Unchecked,
+        }
+    }
+    pub fn message(text: &str) -> Record {
+        Record::new(Direction::Info, text.as_bytes().to_vec())
+    }
+    pub fn with_source(mut self, source: impl Into<String>) -> Record {
+        self.source = Some(source.into());
+        self
+    }
+    pub fn with_conn(mut self, conn: usize) -> Record {
+        self.conn = conn;
+        self
+    }
+    pub fn with_time(mut self, stamp: Timestamp) -> Record {
+        self.time = stamp.time;
+        self.mono = stamp.mono;
+        self
+    }
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Record {
+        self.tag = Some(tag.into());
+        self
+    }
+}
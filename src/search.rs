@@ -0,0 +1,296 @@
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use memchr::memmem;
+use regex::Regex;
+
+use crate::encoding::TextEncoding;
+use crate::history::History;
+use crate::record::{Direction, Record};
+
+/// 每帧用于扫描历史记录的时间，扫描不完下一帧继续，界面和事件处理不会被长时间占用
+const SCAN_BUDGET: Duration = Duration::from_millis(8);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SearchKind {
+    /// 字节序列，`??` 匹配任意一个字节，比如 `55 AA ?? 01`
+    Hex,
+    /// 原始字节中的 ASCII 子串，区分大小写
+    Ascii,
+    /// 按当前编码解码后的文本上的正则表达式
+    Regex,
+}
+impl SearchKind {
+    pub const ALL: [SearchKind; 3] = [SearchKind::Hex, SearchKind::Ascii, SearchKind::Regex];
+    pub fn name(&self) -> &'static str {
+        match self {
+            SearchKind::Hex => "HEX",
+            SearchKind::Ascii => "ASCII",
+            SearchKind::Regex => "正则",
+        }
+    }
+}
+
+enum Pattern {
+    Hex(Vec<Option<u8>>),
+    Ascii(Vec<u8>),
+    Regex(Regex, TextEncoding),
+}
+impl Pattern {
+    fn parse(kind: SearchKind, text: &str, encoding: TextEncoding) -> Result<Pattern, String> {
+        match kind {
+            SearchKind::Hex => {
+                let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+                if digits.is_empty() {
+                    return Err("没有要搜索的字节".to_string());
+                }
+                if !digits.len().is_multiple_of(2) {
+                    return Err("HEX 字符数必须是偶数".to_string());
+                }
+                digits
+                    .chunks(2)
+                    .map(|pair| match pair {
+                        ['?', '?'] => Ok(None),
+                        [hi, lo] => match (hi.to_digit(16), lo.to_digit(16)) {
+                            (Some(hi), Some(lo)) => Ok(Some((hi * 16 + lo) as u8)),
+                            _ => Err(format!("无效的 HEX: {}{}", hi, lo)),
+                        },
+                        _ => unreachable!(),
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(Pattern::Hex)
+            }
+            SearchKind::Ascii => Ok(Pattern::Ascii(text.as_bytes().to_vec())),
+            SearchKind::Regex => Regex::new(text)
+                .map(|re| Pattern::Regex(re, encoding))
+                .map_err(|e| format!("正则表达式错误: {}", e)),
+        }
+    }
+    fn is_match(&self, bytes: &[u8]) -> bool {
+        match self {
+            Pattern::Regex(re, encoding) => re.is_match(&encoding.decode(bytes)),
+            _ => !self.find(bytes).is_empty(),
+        }
+    }
+    /// 所有不重叠的匹配在原始字节中的位置
+    fn find(&self, bytes: &[u8]) -> Vec<Range<usize>> {
+        match self {
+            Pattern::Hex(pattern) => {
+                let mut ranges = Vec::new();
+                let mut start = 0;
+                while start + pattern.len() <= bytes.len() {
+                    let window = &bytes[start..start + pattern.len()];
+                    if window
+                        .iter()
+                        .zip(pattern)
+                        .all(|(b, p)| p.is_none_or(|p| p == *b))
+                    {
+                        ranges.push(start..start + pattern.len());
+                        start += pattern.len();
+                    } else {
+                        start += 1;
+                    }
+                }
+                ranges
+            }
+            Pattern::Ascii(needle) => memmem::find_iter(bytes, needle)
+                .map(|start| start..start + needle.len())
+                .collect(),
+            // 正则匹配的是解码后的文本，换算回原始字节
+            Pattern::Regex(re, encoding) => {
+                let (text, index) = encoding.decode_indexed(bytes);
+                re.find_iter(&text)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| index[m.start()]..index[m.end()])
+                    .collect()
+            }
+        }
+    }
+}
+
+/// 数据区的搜索，在界面刷新时分批扫描历史记录，新收到的记录也会继续扫描
+pub struct Search {
+    pub kind: SearchKind,
+    pub text: String,
+    /// 只显示匹配的记录
+    pub filter: bool,
+    /// 当前的搜索条件，变化后重新扫描
+    key: Option<(SearchKind, String, TextEncoding)>,
+    pattern: Option<Pattern>,
+    pub error: Option<String>,
+    /// 匹配记录的编号，见 `History::dropped`
+    matches: Vec<usize>,
+    /// 下一条要扫描的记录编号
+    scanned: usize,
+    /// 当前选中的匹配在 `matches` 中的位置
+    current: Option<usize>,
+    /// 跳转后需要滚动到的记录序号
+    scroll_to: Option<usize>,
+}
+impl Search {
+    pub fn new() -> Search {
+        Search {
+            kind: SearchKind::Hex,
+            text: String::new(),
+            filter: false,
+            key: None,
+            pattern: None,
+            error: None,
+            matches: Vec::new(),
+            scanned: 0,
+            current: None,
+            scroll_to: None,
+        }
+    }
+    pub fn active(&self) -> bool {
+        self.pattern.is_some()
+    }
+    /// 还有记录没有扫描，需要继续刷新界面
+    pub fn pending(&self, history: &History) -> bool {
+        self.active() && self.scanned < history.dropped() + history.len()
+    }
+    /// 条件变化时重新编译，然后在时间预算内继续扫描
+    pub fn update(&mut self, history: &History, encoding: TextEncoding) {
+        let key = (self.kind, self.text.clone(), encoding);
+        if self.key.as_ref() != Some(&key) {
+            self.matches.clear();
+            self.current = None;
+            self.scanned = 0;
+            (self.pattern, self.error) = match key.1.is_empty() {
+                true => (None, None),
+                false => match Pattern::parse(key.0, &key.1, encoding) {
+                    Ok(pattern) => (Some(pattern), None),
+                    Err(e) => (None, Some(e)),
+                },
+            };
+            self.key = Some(key);
+        }
+        let Some(pattern) = self.pattern.as_ref() else {
+            return;
+        };
+        // 被淘汰的记录不再显示
+        let dropped = history.dropped();
+        let gone = self.matches.partition_point(|&seq| seq < dropped);
+        if gone > 0 {
+            self.matches.drain(..gone);
+            self.current = self
+                .current
+                .and_then(|current| current.checked_sub(gone))
+                .or((!self.matches.is_empty()).then_some(0));
+        }
+        self.scanned = self.scanned.max(dropped);
+        let end = dropped + history.len();
+        let started = Instant::now();
+        while self.scanned < end && started.elapsed() < SCAN_BUDGET {
+            if let Some(record) = history.get(self.scanned - dropped) {
+                if matches(pattern, &record) {
+                    self.matches.push(self.scanned);
+                }
+            }
+            self.scanned += 1;
+        }
+    }
+    /// 记录中要高亮的字节，只在显示时对可见的行计算
+    pub fn find(&self, record: &Record) -> Vec<Range<usize>> {
+        match self.pattern.as_ref() {
+            Some(pattern) if record.direction != Direction::Info => pattern.find(&record.bytes),
+            _ => Vec::new(),
+        }
+    }
+    pub fn is_current(&self, seq: usize) -> bool {
+        self.current.map(|i| self.matches[i]) == Some(seq)
+    }
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+    /// 过滤模式下第 `row` 行对应的记录编号
+    pub fn matched(&self, row: usize) -> Option<usize> {
+        self.matches.get(row).copied()
+    }
+    /// 跳到下一个匹配，`forward` 为 false 时跳到上一个，到头后从另一端继续
+    pub fn jump(&mut self, forward: bool) {
+        let count = self.matches.len();
+        if count == 0 {
+            return;
+        }
+        let current = match (self.current, forward) {
+            (Some(i), true) => (i + 1) % count,
+            (Some(i), false) => (i + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+        self.current = Some(current);
+        self.scroll_to = Some(match self.filter {
+            true => current,
+            false => self.matches[current],
+        });
+    }
+    /// 取出待滚动的位置，过滤模式下是行号，否则是记录编号
+    pub fn take_scroll(&mut self) -> Option<usize> {
+        self.scroll_to.take()
+    }
+}
+
+/// 提示信息不参与搜索
+fn matches(pattern: &Pattern, record: &Record) -> bool {
+    record.direction != Direction::Info && pattern.is_match(&record.bytes)
+}
+
+#[test]
+fn test_search_patterns() {
+    let parse = |kind, text| Pattern::parse(kind, text, TextEncoding::Utf8).unwrap();
+    let bytes = b"\x55\xaa\x10\x01OK\r\n";
+    assert!(parse(SearchKind::Hex, "55 AA ?? 01").is_match(bytes));
+    assert!(parse(SearchKind::Hex, "aa10").is_match(bytes));
+    assert!(!parse(SearchKind::Hex, "55 ?? 01").is_match(bytes));
+    assert!(Pattern::parse(SearchKind::Hex, "55 A", TextEncoding::Utf8).is_err());
+    assert!(Pattern::parse(SearchKind::Hex, "5G", TextEncoding::Utf8).is_err());
+    assert!(Pattern::parse(SearchKind::Hex, " ", TextEncoding::Utf8).is_err());
+    assert!(parse(SearchKind::Ascii, "OK\r").is_match(bytes));
+    assert!(!parse(SearchKind::Ascii, "ok").is_match(bytes));
+    assert!(parse(SearchKind::Regex, r"(?i)ok\r\n$").is_match(bytes));
+    assert!(Pattern::parse(SearchKind::Regex, "(", TextEncoding::Utf8).is_err());
+    assert_eq!(
+        parse(SearchKind::Hex, "?? 55").find(b"\x01\x55\x02\x55\x55"),
+        vec![0..2, 2..4]
+    );
+    assert_eq!(
+        parse(SearchKind::Ascii, "K").find(b"OKOK"),
+        vec![1..2, 3..4]
+    );
+    let gbk = Pattern::parse(SearchKind::Regex, "中.", TextEncoding::Gb18030).unwrap();
+    assert!(gbk.is_match(&[0xD6, 0xD0, 0xCE, 0xC4]));
+    assert_eq!(gbk.find(&[0x41, 0xD6, 0xD0, 0xCE, 0xC4]), vec![1..5]);
+}
+
+#[test]
+fn test_search_history() {
+    let mut history = History::new(4, usize::MAX);
+    let mut search = Search::new();
+    search.text = "AA".to_string();
+    for i in 0..6u8 {
+        history.push(Record::new(Direction::Rx, vec![0xAA * (i % 2), i]));
+    }
+    history.push(Record::message("AA"));
+    search.update(&history, TextEncoding::Utf8);
+    assert!(!search.pending(&history));
+    // 剩下编号 3..7 的记录，其中 3、5 匹配，6 是提示信息
+    assert_eq!(search.match_count(), 2);
+    assert_eq!((search.matched(0), search.matched(1)), (Some(3), Some(5)));
+    search.jump(false);
+    assert!(search.is_current(5));
+    assert_eq!(search.take_scroll(), Some(5));
+    search.jump(true);
+    assert!(search.is_current(3));
+    history.push(Record::new(Direction::Tx, vec![0xAA]));
+    search.update(&history, TextEncoding::Utf8);
+    assert_eq!(search.match_count(), 2);
+    assert_eq!((search.matched(0), search.matched(1)), (Some(5), Some(7)));
+    assert_eq!(search.current(), Some(0));
+    search.text = "BB".to_string();
+    search.update(&history, TextEncoding::Utf8);
+    assert_eq!(search.match_count(), 0);
+}
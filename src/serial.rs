@@ -1,151 +1,477 @@
-use memchr::memmem;
-use std::io;
-
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::io::AsyncReadExt;
-use tokio::sync::{mpsc, Mutex};
-use tokio::task::JoinHandle;
-use tokio_serial::{SerialPort, SerialPortBuilderExt, SerialStream};
-
-// use tokio_util::codec::{FramedRead, LinesCodec};
-use crate::DATA;
-use tokio_util::{
-    bytes::BytesMut,
-    codec::{Decoder, Encoder},
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_serial::{
+    SerialPort, SerialPortBuilder, SerialPortBuilderExt, SerialPortInfo, SerialPortType,
+    SerialStream,
 };
 
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
-struct LineCodec;
-impl LineCodec {
-    pub fn new() -> LineCodec {
-        LineCodec {}
-    }
-}
-const DELIMITER: &[u8] = &[0x55, 0xAA];
-impl Decoder for LineCodec {
-    // type Item = Vec<BytesMut>;
-    type Item = BytesMut;
-    type Error = io::Error;
-
-    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // let newline = src.as_ref().iter().position(|b| *b == b'\n');
-        // let frame = src.windows(2).position(|w| w == DELIMITER);
-        // let frame = memmem::find(src, DELIMITER);
-        // if let Some(index) = frame {
-        //     let line = src.split_to(index + 1);
-        //     return Ok(Some(line));
-        // }
-        //
-        let frame: Vec<usize> = memmem::find_iter(src, DELIMITER).collect();
-        println!("{:?}", frame);
-        // println!("index {:?}", frame);
-        // for ele in frame {}
-        // if frame.len() > 1 {
-        //     return Ok(Some(line));
-        // }
-        Ok(None)
-    }
-
-    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        match self.decode(buf)? {
-            Some(frame) => Ok(Some(frame)),
-            None => {
-                if buf.is_empty() {
-                    Ok(None)
-                } else {
-                    Err(io::Error::new(io::ErrorKind::Other, "bytes remaining on stream").into())
-                }
-            }
-        }
-    }
+use crate::codec::{FrameCodec, FrameReader};
+use crate::event::{Event, EventSender};
+use crate::record::{Direction, Record};
+use crate::transport::{queue_error, Outgoing, Session, Status, Transport, Worker};
 
-    fn framed<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Sized>(
-        self,
-        io: T,
-    ) -> tokio_util::codec::Framed<T, Self>
-    where
-        Self: Sized,
-    {
-        tokio_util::codec::Framed::new(io, self)
-    }
+/// 串口的输出控制线
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Line {
+    Dtr,
+    Rts,
+}
+impl Line {
+    const ALL: [Line; 2] = [Line::Dtr, Line::Rts];
 }
 
-impl Encoder<String> for LineCodec {
-    type Error = io::Error;
+/// 发给串口任务的控制命令
+#[derive(Clone, Copy, Debug)]
+pub enum Control {
+    Set(Line, bool),
+    /// 控制线翻转成相反的电平保持一段时间后恢复，用于复位开发板
+    Pulse(Line, Duration),
+}
 
-    fn encode(&mut self, _item: String, _dst: &mut BytesMut) -> Result<(), Self::Error> {
-        Ok(())
-    }
+/// 串口的输入状态线
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ModemLines {
+    pub cts: bool,
+    pub dsr: bool,
+    pub cd: bool,
+    pub ri: bool,
 }
 
+/// 输入状态线的轮询间隔
+const MODEM_POLL: Duration = Duration::from_millis(200);
+
 pub struct Serial {
-    shutdown_tx: mpsc::Sender<()>,
-    handle: Mutex<Option<JoinHandle<()>>>,
+    worker: Worker,
+    write_tx: mpsc::Sender<Outgoing>,
+    control_tx: mpsc::Sender<Control>,
 }
 impl Serial {
-    pub fn new(path: &str, baud_rate: u32, data_bits: u8, stop_bits: u8) -> Self {
-        let port = tokio_serial::available_ports().unwrap();
-        for ele in port {
-            println!("{}:{:?}", ele.port_name, ele.port_type);
-        }
-        let (shutdown_tx, shutdown_rx): (mpsc::Sender<()>, mpsc::Receiver<()>) = mpsc::channel(1);
-        let port = tokio_serial::new(path, baud_rate)
-            .data_bits(tokio_serial::DataBits::try_from(data_bits).unwrap())
-            .stop_bits(tokio_serial::StopBits::try_from(stop_bits).unwrap())
+    pub fn open(
+        builder: SerialPortBuilder,
+        codec: FrameCodec,
+        events: EventSender,
+    ) -> Result<Self, String> {
+        let mut port = builder
             .open_native_async()
-            .unwrap();
-        let handle = tokio::spawn(Self::read(port, shutdown_rx));
-        Self {
-            shutdown_tx: shutdown_tx,
-            handle: Mutex::new(Some(handle)),
-        }
+            .map_err(|e| format!("打开串口失败: {}", e))?;
+        port.set_timeout(Duration::from_millis(0))
+            .map_err(|e| format!("设置串口超时失败: {}", e))?;
+        let (write_tx, write_rx) = mpsc::channel(32);
+        let (control_tx, control_rx) = mpsc::channel(8);
+        let worker = Worker::spawn(events, |session| {
+            Self::read(port, codec, write_rx, control_rx, session)
+        });
+        Ok(Self {
+            worker,
+            write_tx,
+            control_tx,
+        })
     }
     pub async fn read(
-        mut port: SerialStream,
-        mut shutdown_rx: mpsc::Receiver<()>,
+        port: SerialStream,
+        codec: FrameCodec,
+        mut write_rx: mpsc::Receiver<Outgoing>,
+        mut control_rx: mpsc::Receiver<Control>,
+        mut session: Session,
     ) {
-        // let mut reader = LineCodec.framed(port);
-        // let mut reader = FramedRead::new(port, LineCodec::new());
-        port.set_timeout(Duration::from_millis(0)).unwrap();
-        let mut buf = [0; 128];
+        let events = session.events.clone();
+        // 不拆分读写，控制线的操作需要完整的串口
+        let mut reader = FrameReader::new(port, codec);
+        let mut levels = Levels::default();
+        let mut pending_break: Option<PendingBreak> = None;
+        let mut lines: Option<ModemLines> = None;
+        let mut poll = tokio::time::interval(MODEM_POLL);
+        session.connected();
         loop {
+            let pulse_end = levels.deadline();
+            let break_end = pending_break.as_ref().map(|pending| pending.end);
             tokio::select! {
-                res = port.read(&mut buf) => {
+                res = reader.next() => {
                     match res {
-                        Ok(n) => {
-                            let mut b = BytesMut::new();
-                            b.extend_from_slice(&buf[..n]);
-                            let sys_time = SystemTime::now()
-                                .duration_since(UNIX_EPOCH)
-                                .expect("Time went backwards")
-                                .as_millis() as u64;
-                            let content:String = hex::encode(&buf[..n])
-                                .chars()
-                                .enumerate()
-                                .flat_map(|(i, c)| {
-                                    if i > 0 && i % 2 == 0 { Some(' ') } else { None }
-                                        .into_iter()
-                                        .chain(std::iter::once(c.to_ascii_uppercase()))
-                                })
-                                .collect();
-                            DATA.lock().unwrap().push_back(format!("{}--{}",sys_time, content));
-                        },
-                        Err(e) => eprintln!("Read error: {}", e),
+                        Some(Ok((frame, received))) => {
+                            let record = Record::new(Direction::Rx, frame.to_vec());
+                            events.send(Event::RxFrame(record.with_time(received)));
+                        }
+                        // 串口出错后通常一直返回错误，继续读只会空转
+                        Some(Err(e)) => {
+                            events.send(Event::Error(format!("读取失败: {}", e)));
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+                // BREAK 和数据走同一个队列，BREAK 期间不取后面的帧，保证先后顺序
+                Some(frame) = write_rx.recv(), if pending_break.is_none() => {
+                    let Some(duration) = frame.break_before else {
+                        write_frame(reader.get_mut(), frame.bytes, &events).await;
+                        continue;
+                    };
+                    match PendingBreak::start(reader.get_mut(), frame, duration) {
+                        Ok(pending) => pending_break = Some(pending),
+                        Err(e) => events.send(Event::Error(format!("发送 BREAK 失败: {}", e))),
+                    }
+                }
+                _ = tokio::time::sleep_until(break_end.unwrap_or_else(Instant::now)),
+                    if break_end.is_some() => {
+                    if let Some(pending) = pending_break.take() {
+                        pending.finish(reader.get_mut(), &events).await;
+                    }
+                }
+                Some(control) = control_rx.recv() => {
+                    if let Err(e) = levels.apply(reader.get_mut(), control) {
+                        events.send(Event::Error(format!("控制线操作失败: {}", e)));
+                    }
+                }
+                _ = tokio::time::sleep_until(pulse_end.unwrap_or_else(Instant::now)),
+                    if pulse_end.is_some() => {
+                    if let Err(e) = levels.expire(reader.get_mut(), Instant::now()) {
+                        events.send(Event::Error(format!("控制线操作失败: {}", e)));
+                    }
+                }
+                _ = poll.tick() => {
+                    // 部分驱动不支持读取状态线，读不到时不更新
+                    if let Ok(current) = read_modem_lines(reader.get_mut()) {
+                        if lines != Some(current) {
+                            lines = Some(current);
+                            events.send(Event::ModemChanged(current));
+                        }
                     }
                 }
-                _ = shutdown_rx.recv() => {
+                _ = session.shutdown_rx.recv() => {
                     println!("Shutting down worker");
                     break;
                 }
             }
         }
     }
-    pub fn close(&self) {
-        futures::executor::block_on(async {
-            if let Some(handle) = self.handle.lock().await.take() {
-                let _ = self.shutdown_tx.send(()).await;
-                handle.await.unwrap();
+    pub fn send_break(&self, duration: Duration) -> Result<(), String> {
+        let mut frame = Outgoing::new(Vec::new());
+        frame.break_before = Some(duration);
+        self.write(frame)
+    }
+    pub fn control(&self, control: Control) -> Result<(), String> {
+        self.control_tx.try_send(control).map_err(queue_error)
+    }
+}
+impl Transport for Serial {
+    fn write(&self, frame: Outgoing) -> Result<(), String> {
+        self.write_tx.try_send(frame).map_err(queue_error)
+    }
+    fn status(&self) -> Status {
+        self.worker.status()
+    }
+    fn close(&self) {
+        self.worker.close();
+    }
+    fn as_serial(&self) -> Option<&Serial> {
+        Some(self)
+    }
+}
+
+/// 串口上的控制操作，测试时用假的串口代替
+trait PortControl {
+    fn set_line(&mut self, line: Line, level: bool) -> tokio_serial::Result<()>;
+    fn set_break(&mut self, on: bool) -> tokio_serial::Result<()>;
+}
+impl PortControl for SerialStream {
+    fn set_break(&mut self, on: bool) -> tokio_serial::Result<()> {
+        match on {
+            true => SerialPort::set_break(self),
+            false => self.clear_break(),
+        }
+    }
+    fn set_line(&mut self, line: Line, level: bool) -> tokio_serial::Result<()> {
+        match line {
+            Line::Dtr => self.write_data_terminal_ready(level),
+            Line::Rts => self.write_request_to_send(level),
+        }
+    }
+}
+
+/// DTR、RTS 设置的电平和进行中的脉冲，脉冲到时由任务循环恢复，期间照常读写
+#[derive(Default)]
+struct Levels {
+    levels: [bool; 2],
+    pulse_end: [Option<Instant>; 2],
+}
+impl Levels {
+    fn apply(&mut self, port: &mut impl PortControl, control: Control) -> tokio_serial::Result<()> {
+        match control {
+            // 脉冲期间重新设置电平时以新的设置为准
+            Control::Set(line, level) => {
+                port.set_line(line, level)?;
+                self.levels[line as usize] = level;
+                self.pulse_end[line as usize] = None;
             }
-        });
+            // 总是翻转当前电平，已经拉高的线也能产生跳变
+            Control::Pulse(line, duration) => {
+                port.set_line(line, !self.levels[line as usize])?;
+                self.pulse_end[line as usize] = Some(Instant::now() + duration);
+            }
+        }
+        Ok(())
+    }
+    /// 最早结束的脉冲
+    fn deadline(&self) -> Option<Instant> {
+        self.pulse_end.iter().flatten().min().copied()
+    }
+    /// 把已经到时的脉冲恢复成原来的电平
+    fn expire(&mut self, port: &mut impl PortControl, now: Instant) -> tokio_serial::Result<()> {
+        for line in Line::ALL {
+            if self.pulse_end[line as usize].is_some_and(|end| end <= now) {
+                self.pulse_end[line as usize] = None;
+                port.set_line(line, self.levels[line as usize])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 正在发送的 BREAK，到时清除后再发出同一帧的数据
+struct PendingBreak {
+    end: Instant,
+    frame: Outgoing,
+}
+impl PendingBreak {
+    fn start(
+        port: &mut impl PortControl,
+        frame: Outgoing,
+        duration: Duration,
+    ) -> tokio_serial::Result<PendingBreak> {
+        port.set_break(true)?;
+        Ok(PendingBreak {
+            end: Instant::now() + duration,
+            frame,
+        })
+    }
+    async fn finish<P>(self, port: &mut P, events: &EventSender)
+    where
+        P: PortControl + AsyncWrite + Unpin,
+    {
+        if let Err(e) = port.set_break(false) {
+            events.send(Event::Error(format!("发送 BREAK 失败: {}", e)));
+            return;
+        }
+        let record = Record::new(Direction::Tx, Vec::new());
+        events.send(Event::TxFrame(record.with_tag("BREAK")));
+        write_frame(port, self.frame.bytes, events).await;
+    }
+}
+
+async fn write_frame(port: &mut (impl AsyncWrite + Unpin), bytes: Vec<u8>, events: &EventSender) {
+    if bytes.is_empty() {
+        return;
     }
+    match port.write_all(&bytes).await {
+        Ok(()) => events.send(Event::TxFrame(Record::new(Direction::Tx, bytes))),
+        Err(e) => events.send(Event::Error(format!("发送失败: {}", e))),
+    }
+}
+
+fn read_modem_lines(port: &mut SerialStream) -> tokio_serial::Result<ModemLines> {
+    Ok(ModemLines {
+        cts: port.read_clear_to_send()?,
+        dsr: port.read_data_set_ready()?,
+        cd: port.read_carrier_detect()?,
+        ri: port.read_ring_indicator()?,
+    })
+}
+
+/// 定时扫描可用串口，列表有变化时发出事件
+pub async fn watch_ports(events: EventSender) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    let mut last: Option<Vec<SerialPortInfo>> = None;
+    loop {
+        interval.tick().await;
+        if let Ok(mut ports) = tokio_serial::available_ports() {
+            ports.sort_by(|a, b| a.port_name.cmp(&b.port_name));
+            if last.as_ref() != Some(&ports) {
+                last = Some(ports.clone());
+                events.send(Event::PortListChanged(ports));
+            }
+        }
+    }
+}
+
+pub fn usb_serial_number(port: &SerialPortInfo) -> Option<&str> {
+    match &port.port_type {
+        SerialPortType::UsbPort(usb) => usb.serial_number.as_deref(),
+        _ => None,
+    }
+}
+
+/// 下拉框中显示的串口说明，USB 串口带上 VID:PID、厂商、产品和序列号
+pub fn port_label(port: &SerialPortInfo) -> String {
+    let SerialPortType::UsbPort(usb) = &port.port_type else {
+        return port.port_name.clone();
+    };
+    let mut label = format!("{} [{:04x}:{:04x}", port.port_name, usb.vid, usb.pid);
+    for text in [&usb.manufacturer, &usb.product].into_iter().flatten() {
+        label.push(' ');
+        label.push_str(text);
+    }
+    if let Some(serial_number) = usb.serial_number.as_ref() {
+        label.push_str(" SN:");
+        label.push_str(serial_number);
+    }
+    label.push(']');
+    label
+}
+
+#[test]
+fn test_port_label() {
+    let mut port = SerialPortInfo {
+        port_name: "/dev/ttyUSB1".into(),
+        port_type: SerialPortType::UsbPort(tokio_serial::UsbPortInfo {
+            vid: 0x0403,
+            pid: 0x6001,
+            serial_number: Some("A1".into()),
+            manufacturer: Some("FTDI".into()),
+            product: None,
+        }),
+    };
+    assert_eq!(port_label(&port), "/dev/ttyUSB1 [0403:6001 FTDI SN:A1]");
+    assert_eq!(usb_serial_number(&port), Some("A1"));
+    port.port_type = SerialPortType::Unknown;
+    assert_eq!(port_label(&port), "/dev/ttyUSB1");
+}
+
+#[test]
+fn test_open_error() {
+    use crate::codec::Framing;
+
+    let (events, _events_rx) = EventSender::channel(None);
+    let builder = tokio_serial::new("/nonexistent/tty", 115200);
+    let res = Serial::open(builder, FrameCodec::new(Framing::Raw), events);
+    assert!(res.err().is_some_and(|e| e.starts_with("打开串口失败")));
+}
+
+#[cfg(test)]
+#[derive(PartialEq, Debug)]
+enum Op {
+    Line(Line, bool),
+    Break(bool),
+    Write(Vec<u8>),
+}
+#[cfg(test)]
+#[derive(Default)]
+struct FakePort {
+    ops: Vec<Op>,
+}
+#[cfg(test)]
+impl PortControl for FakePort {
+    fn set_line(&mut self, line: Line, level: bool) -> tokio_serial::Result<()> {
+        self.ops.push(Op::Line(line, level));
+        Ok(())
+    }
+    fn set_break(&mut self, on: bool) -> tokio_serial::Result<()> {
+        self.ops.push(Op::Break(on));
+        Ok(())
+    }
+}
+#[cfg(test)]
+impl AsyncWrite for FakePort {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        self.ops.push(Op::Write(buf.to_vec()));
+        std::task::Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn test_pulse_restores_level() {
+    let mut port = FakePort::default();
+    let mut levels = Levels::default();
+    levels
+        .apply(&mut port, Control::Set(Line::Dtr, true))
+        .unwrap();
+    levels
+        .apply(
+            &mut port,
+            Control::Pulse(Line::Dtr, Duration::from_millis(100)),
+        )
+        .unwrap();
+    levels
+        .apply(
+            &mut port,
+            Control::Pulse(Line::Rts, Duration::from_millis(50)),
+        )
+        .unwrap();
+    let start = Instant::now();
+    // 已经拉高的 DTR 脉冲时先拉低
+    assert_eq!(
+        port.ops[1..],
+        [Op::Line(Line::Dtr, false), Op::Line(Line::Rts, true)]
+    );
+    // 最早到时的是 RTS，DTR 还在脉冲中
+    let deadline = levels.deadline().unwrap();
+    assert!(deadline <= start + Duration::from_millis(50));
+    levels.expire(&mut port, deadline).unwrap();
+    assert_eq!(port.ops.last(), Some(&Op::Line(Line::Rts, false)));
+    assert!(levels.deadline().is_some());
+    levels
+        .expire(&mut port, start + Duration::from_millis(100))
+        .unwrap();
+    assert_eq!(port.ops.last(), Some(&Op::Line(Line::Dtr, true)));
+    assert_eq!(levels.deadline(), None);
+    // 脉冲期间重新设置电平，到时不再恢复
+    levels
+        .apply(
+            &mut port,
+            Control::Pulse(Line::Rts, Duration::from_millis(10)),
+        )
+        .unwrap();
+    levels
+        .apply(&mut port, Control::Set(Line::Rts, false))
+        .unwrap();
+    assert_eq!(levels.deadline(), None);
+    assert_eq!(port.ops.len(), 7);
+}
+
+#[tokio::test]
+async fn test_break_before_payload() {
+    let (events, events_rx) = EventSender::channel(None);
+    let mut port = FakePort::default();
+    let mut frame = Outgoing::new(vec![0x55, 0xAA]);
+    frame.break_before = Some(Duration::from_millis(5));
+    let pending = PendingBreak::start(&mut port, frame, Duration::from_millis(5)).unwrap();
+    // BREAK 结束前数据不会写出
+    assert_eq!(port.ops, vec![Op::Break(true)]);
+    assert!(events_rx.try_recv().is_err());
+    tokio::time::sleep_until(pending.end).await;
+    pending.finish(&mut port, &events).await;
+    assert_eq!(
+        port.ops,
+        vec![
+            Op::Break(true),
+            Op::Break(false),
+            Op::Write(vec![0x55, 0xAA])
+        ]
+    );
+    let tx: Vec<Record> = std::iter::from_fn(|| events_rx.try_recv().ok())
+        .filter_map(|(_, event)| match event {
+            Event::TxFrame(record) => Some(record),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(tx.len(), 2);
+    assert_eq!(tx[0].tag.as_deref(), Some("BREAK"));
+    assert_eq!(tx[1].bytes, vec![0x55, 0xAA]);
 }
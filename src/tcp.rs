@@ -0,0 +1,135 @@
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+use crate::codec::{FrameCodec, FrameReader};
+use crate::event::{Event, EventSender};
+use crate::record::{Direction, Record};
+use crate::transport::{queue_error, Outgoing, Session, Status, Transport, Worker};
+
+pub struct Tcp {
+    worker: Worker,
+    write_tx: mpsc::Sender<Vec<u8>>,
+}
+impl Tcp {
+    pub fn open(host: &str, port: u16, codec: FrameCodec, events: EventSender) -> Self {
+        let (write_tx, write_rx) = mpsc::channel(32);
+        let addr = format!("{}:{}", host, port);
+        let worker = Worker::spawn(events, |session| Self::read(addr, codec, write_rx, session));
+        Self { worker, write_tx }
+    }
+    pub async fn read(
+        addr: String,
+        codec: FrameCodec,
+        mut write_rx: mpsc::Receiver<Vec<u8>>,
+        mut session: Session,
+    ) {
+        let events = session.events.clone();
+        // 连接过程中也要能响应断开
+        let stream = tokio::select! {
+            res = TcpStream::connect(&addr) => match res {
+                Ok(stream) => stream,
+                Err(e) => {
+                    events.send(Event::Error(format!("连接 {} 失败: {}", addr, e)));
+                    return;
+                }
+            },
+            _ = session.shutdown_rx.recv() => return,
+        };
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = FrameReader::new(reader, codec);
+        session.connected();
+        loop {
+            tokio::select! {
+                res = reader.next() => {
+                    match res {
+                        None => break,
+                        Some(Ok((frame, received))) => {
+                            let record = Record::new(Direction::Rx, frame.to_vec());
+                            events.send(Event::RxFrame(record.with_time(received)));
+                        }
+                        Some(Err(e)) => {
+                            events.send(Event::Error(format!("读取失败: {}", e)));
+                            break;
+                        }
+                    }
+                }
+                Some(bytes) = write_rx.recv() => {
+                    if let Err(e) = writer.write_all(&bytes).await {
+                        events.send(Event::Error(format!("发送失败: {}", e)));
+                        break;
+                    }
+                    events.send(Event::TxFrame(Record::new(Direction::Tx, bytes)));
+                }
+                _ = session.shutdown_rx.recv() => {
+                    println!("Shutting down worker");
+                    break;
+                }
+            }
+        }
+    }
+}
+impl Transport for Tcp {
+    fn write(&self, frame: Outgoing) -> Result<(), String> {
+        self.write_tx.try_send(frame.bytes).map_err(queue_error)
+    }
+    fn status(&self) -> Status {
+        self.worker.status()
+    }
+    fn close(&self) {
+        self.worker.close();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tcp_events() {
+    use crate::codec::Framing;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (events, events_rx) = EventSender::channel(None);
+    let codec = FrameCodec::new(Framing::Raw);
+    let tcp = Tcp::open("127.0.0.1", port, codec, events.with_conn(1));
+    let (mut peer, _) = listener.accept().await.unwrap();
+    peer.write_all(&[0x55, 0xAA]).await.unwrap();
+    tcp.write(Outgoing::new(vec![0x01, 0x02])).unwrap();
+    let mut buf = [0; 2];
+    peer.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [0x01, 0x02]);
+    drop(peer);
+
+    let mut received = Vec::new();
+    while !matches!(received.last(), Some(Event::Disconnected(_))) {
+        match events_rx.try_recv() {
+            Ok((conn, event)) => {
+                assert_eq!(conn, 1);
+                received.push(event);
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+        }
+    }
+    assert!(matches!(received[0], Event::Connected(None)));
+    assert_eq!(tcp.status(), Status::Closed);
+    // 断开后发送失败要报给界面
+    assert_eq!(
+        tcp.write(Outgoing::new(vec![0x03])),
+        Err("连接已关闭".to_string())
+    );
+    let rx: Vec<&Record> = received
+        .iter()
+        .filter_map(|event| match event {
+            Event::RxFrame(record) => Some(record),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(rx.len(), 1);
+    assert_eq!(rx[0].bytes, vec![0x55, 0xAA]);
+    assert_eq!(rx[0].conn, 1);
+    assert!(received
+        .iter()
+        .any(|event| matches!(event, Event::TxFrame(record) if record.bytes == [0x01, 0x02])));
+    tcp.close();
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex as StdMutex};
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+use crate::codec::{FrameCodec, FrameReader};
+use crate::event::{Event, EventSender};
+use crate::record::{Direction, Record};
+use crate::transport::{queue_error, Outgoing, Session, Status, Transport, Worker};
+
+type Clients = Arc<StdMutex<HashMap<SocketAddr, mpsc::Sender<Vec<u8>>>>>;
+
+pub struct TcpServer {
+    worker: Worker,
+    clients: Clients,
+}
+impl TcpServer {
+    pub fn open(bind_host: &str, port: u16, codec: FrameCodec, events: EventSender) -> Self {
+        let clients: Clients = Arc::new(StdMutex::new(HashMap::new()));
+        let bind = format!("{}:{}", bind_host, port);
+        let worker = {
+            let clients = clients.clone();
+            Worker::spawn(events, |session| {
+                Self::accept(bind, codec, clients, session)
+            })
+        };
+        Self { worker, clients }
+    }
+    pub async fn accept(bind: String, codec: FrameCodec, clients: Clients, mut session: Session) {
+        let events = session.events.clone();
+        let listener = match TcpListener::bind(&bind).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                events.send(Event::Error(format!("监听 {} 失败: {}", bind, e)));
+                return;
+            }
+        };
+        session.connected();
+        let mut tasks = JoinSet::new();
+        loop {
+            tokio::select! {
+                res = listener.accept() => {
+                    match res {
+                        Ok((stream, addr)) => {
+                            let (write_tx, write_rx) = mpsc::channel(32);
+                            clients.lock().unwrap().insert(addr, write_tx);
+                            events.send(Event::Connected(Some(addr.to_string())));
+                            let codec = codec.clone();
+                            let events = events.clone();
+                            let clients = clients.clone();
+                            tasks.spawn(Self::read(stream, addr, codec, events, clients, write_rx));
+                        }
+                        Err(e) => events.send(Event::Error(format!("接受连接失败: {}", e))),
+                    }
+                }
+                Some(_) = tasks.join_next(), if !tasks.is_empty() => {}
+                _ = session.shutdown_rx.recv() => {
+                    println!("Shutting down worker");
+                    break;
+                }
+            }
+        }
+        // 中止剩下的客户端任务，它们不会再发出断开事件，这里补上
+        tasks.shutdown().await;
+        for addr in clients.lock().unwrap().drain().map(|(addr, _)| addr) {
+            events.send(Event::Disconnected(Some(addr.to_string())));
+        }
+    }
+    async fn read(
+        stream: TcpStream,
+        addr: SocketAddr,
+        codec: FrameCodec,
+        events: EventSender,
+        clients: Clients,
+        mut write_rx: mpsc::Receiver<Vec<u8>>,
+    ) {
+        let tag = addr.to_string();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = FrameReader::new(reader, codec);
+        loop {
+            tokio::select! {
+                res = reader.next() => {
+                    match res {
+                        None => break,
+                        Some(Ok((frame, received))) => {
+                            let record = Record::new(Direction::Rx, frame.to_vec());
+                            let record = record.with_time(received).with_source(&tag);
+                            events.send(Event::RxFrame(record));
+                        }
+                        Some(Err(e)) => {
+                            events.send(Event::Error(format!("{} 读取失败: {}", tag, e)));
+                            break;
+                        }
+                    }
+                }
+                Some(bytes) = write_rx.recv() => {
+                    if let Err(e) = writer.write_all(&bytes).await {
+                        events.send(Event::Error(format!("{} 发送失败: {}", tag, e)));
+                        break;
+                    }
+                    let record = Record::new(Direction::Tx, bytes);
+                    events.send(Event::TxFrame(record.with_source(&tag)));
+                }
+            }
+        }
+        clients.lock().unwrap().remove(&addr);
+        events.send(Event::Disconnected(Some(tag)));
+    }
+}
+impl Transport for TcpServer {
+    /// `target` 为 `None` 时发送给所有客户端
+    fn write(&self, frame: Outgoing) -> Result<(), String> {
+        let clients = self.clients.lock().unwrap();
+        let targets: Vec<_> = clients
+            .iter()
+            .filter(|(addr, _)| frame.target.is_none_or(|target| target == **addr))
+            .collect();
+        if targets.is_empty() {
+            return Err(match frame.target {
+                Some(target) => format!("客户端 {} 已断开", target),
+                None => "没有已连接的客户端".to_string(),
+            });
+        }
+        // 某个客户端失败不影响发给其它客户端
+        let mut errors = Vec::new();
+        for (addr, write_tx) in targets {
+            if let Err(e) = write_tx.try_send(frame.bytes.clone()) {
+                errors.push(format!("{}: {}", addr, queue_error(e)));
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors.join("; ")),
+        }
+    }
+    fn status(&self) -> Status {
+        self.worker.status()
+    }
+    fn close(&self) {
+        self.worker.close();
+    }
+    /// 当前已连接的客户端，按地址排序
+    fn clients(&self) -> Option<Vec<SocketAddr>> {
+        let mut clients: Vec<SocketAddr> = self.clients.lock().unwrap().keys().copied().collect();
+        clients.sort();
+        Some(clients)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tcp_server_clients() {
+    use crate::codec::Framing;
+    use crate::event::wait_for;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let (events, events_rx) = EventSender::channel(None);
+    let codec = FrameCodec::new(Framing::Raw);
+    let server = TcpServer::open("127.0.0.1", port, codec, events);
+    wait_for(&events_rx, |event| matches!(event, Event::Connected(None))).await;
+    let mut a = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let mut b = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let (a_addr, b_addr) = (a.local_addr().unwrap(), b.local_addr().unwrap());
+    let connected = |event: &Event| matches!(event, Event::Connected(Some(_)));
+    let mut received = wait_for(&events_rx, connected).await;
+    received.extend(wait_for(&events_rx, connected).await);
+    for addr in [a_addr, b_addr] {
+        let tag = Some(addr.to_string());
+        assert!(received
+            .iter()
+            .any(|event| matches!(event, Event::Connected(source) if *source == tag)));
+    }
+
+    // 收到的数据带上客户端地址
+    a.write_all(b"A").await.unwrap();
+    let rx = wait_for(&events_rx, |event| matches!(event, Event::RxFrame(_))).await;
+    let Some(Event::RxFrame(record)) = rx.last() else {
+        unreachable!()
+    };
+    assert_eq!(record.bytes, b"A");
+    assert_eq!(record.source, Some(a_addr.to_string()));
+
+    // 指定目标时只发给这个客户端
+    let mut frame = Outgoing::new(vec![0x01]);
+    frame.target = Some(a_addr);
+    server.write(frame).unwrap();
+    server.write(Outgoing::new(vec![0x02])).unwrap();
+    let mut buf = [0; 2];
+    a.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [0x01, 0x02]);
+    let mut buf = [0; 1];
+    b.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [0x02]);
+    let read = tokio::time::timeout(Duration::from_millis(100), b.read(&mut buf)).await;
+    assert!(read.is_err());
+    let mut tx = Vec::new();
+    while tx.len() < 3 {
+        if let Some(Event::TxFrame(record)) =
+            wait_for(&events_rx, |event| matches!(event, Event::TxFrame(_)))
+                .await
+                .pop()
+        {
+            tx.push((record.source.unwrap(), record.bytes));
+        }
+    }
+    tx.sort();
+    let mut expected = vec![
+        (a_addr.to_string(), vec![0x01]),
+        (a_addr.to_string(), vec![0x02]),
+        (b_addr.to_string(), vec![0x02]),
+    ];
+    expected.sort();
+    assert_eq!(tx, expected);
+
+    // 客户端断开后不再出现在列表中，发给它会报错
+    drop(a);
+    let tag = Some(a_addr.to_string());
+    wait_for(
+        &events_rx,
+        |event| matches!(event, Event::Disconnected(source) if *source == tag),
+    )
+    .await;
+    assert_eq!(server.clients(), Some(vec![b_addr]));
+    let mut frame = Outgoing::new(vec![0x03]);
+    frame.target = Some(a_addr);
+    assert_eq!(
+        server.write(frame),
+        Err(format!("客户端 {} 已断开", a_addr))
+    );
+    // 停止监听时还连着的客户端也要记录断开
+    server.close();
+    assert_eq!(server.status(), Status::Closed);
+    let tag = Some(b_addr.to_string());
+    let received = wait_for(&events_rx, |event| {
+        matches!(event, Event::Disconnected(None))
+    })
+    .await;
+    assert!(received
+        .iter()
+        .any(|event| matches!(event, Event::Disconnected(source) if *source == tag)));
+}
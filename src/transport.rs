@@ -0,0 +1,160 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+
+use crate::event::{Event, EventSender};
+use crate::serial::Serial;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Status {
+    Connecting,
+    Connected,
+    Closed,
+}
+
+/// 要发送的一帧
+pub struct Outgoing {
+    pub bytes: Vec<u8>,
+    /// TCP server 的目标客户端，`None` 时发给所有客户端
+    pub target: Option<SocketAddr>,
+    /// WebSocket 按文本帧发送，`bytes` 为 UTF-8 文本
+    pub text: bool,
+    /// 串口在数据之前先发指定时长的 BREAK
+    pub break_before: Option<Duration>,
+}
+impl Outgoing {
+    pub fn new(bytes: Vec<u8>) -> Outgoing {
+        Outgoing {
+            bytes,
+            target: None,
+            text: false,
+            break_before: None,
+        }
+    }
+}
+
+/// 各种通讯方式的公共接口，由各自的 `open` 创建，收到的数据和状态变化通过事件发给界面
+pub trait Transport: Send {
+    /// 只放入发送队列，写出的结果通过事件返回
+    fn write(&self, frame: Outgoing) -> Result<(), String>;
+    fn status(&self) -> Status;
+    /// 等待后台任务退出
+    fn close(&self);
+    /// 有多个对端的连接返回当前的客户端列表
+    fn clients(&self) -> Option<Vec<SocketAddr>> {
+        None
+    }
+    /// 串口的控制线等专有功能通过它访问
+    fn as_serial(&self) -> Option<&Serial> {
+        None
+    }
+}
+
+/// 放入后台任务的队列失败时给界面显示的说明
+pub fn queue_error<T>(e: TrySendError<T>) -> String {
+    match e {
+        TrySendError::Full(_) => "发送队列已满，请稍后再试".to_string(),
+        TrySendError::Closed(_) => "连接已关闭".to_string(),
+    }
+}
+
+/// 后台任务运行时持有的上下文
+pub struct Session {
+    pub events: EventSender,
+    pub shutdown_rx: mpsc::Receiver<()>,
+    status: Arc<StdMutex<Status>>,
+}
+impl Session {
+    pub fn connected(&self) {
+        *self.status.lock().unwrap() = Status::Connected;
+        self.events.send(Event::Connected(None));
+    }
+}
+
+/// 等待后台任务退出的最长时间，写操作卡住（比如流控阻塞、对端不读）时强制结束
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 通讯方式共用的后台任务管理：启动、通知退出和连接状态
+pub struct Worker {
+    shutdown_tx: mpsc::Sender<()>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+    status: Arc<StdMutex<Status>>,
+    events: EventSender,
+}
+impl Worker {
+    /// 任务结束时统一标记为已关闭并发出断开事件
+    pub fn spawn<F, Fut>(events: EventSender, run: F) -> Worker
+    where
+        F: FnOnce(Session) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (shutdown_tx, shutdown_rx) = mpsc::channel(1);
+        let status = Arc::new(StdMutex::new(Status::Connecting));
+        let session = Session {
+            events: events.clone(),
+            shutdown_rx,
+            status: status.clone(),
+        };
+        let task = run(session);
+        let done = status.clone();
+        let finished = events.clone();
+        let handle = tokio::spawn(async move {
+            task.await;
+            *done.lock().unwrap() = Status::Closed;
+            finished.send(Event::Disconnected(None));
+        });
+        Worker {
+            shutdown_tx,
+            handle: Mutex::new(Some(handle)),
+            status,
+            events,
+        }
+    }
+    pub fn status(&self) -> Status {
+        *self.status.lock().unwrap()
+    }
+    pub fn close(&self) {
+        futures::executor::block_on(async {
+            if let Some(mut handle) = self.handle.lock().await.take() {
+                let _ = self.shutdown_tx.try_send(());
+                match tokio::time::timeout(CLOSE_TIMEOUT, &mut handle).await {
+                    Ok(result) => result.unwrap(),
+                    // 被中止的任务不会走到结束时的处理，这里补上
+                    Err(_) => {
+                        eprintln!("Worker did not stop in time, aborting");
+                        handle.abort();
+                        *self.status.lock().unwrap() = Status::Closed;
+                        self.events.send(Event::Disconnected(None));
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_worker_close_timeout() {
+    use std::time::Instant;
+
+    let (events, events_rx) = EventSender::channel(None);
+    // 不理会退出通知的任务，模拟卡在写操作上
+    let worker = Worker::spawn(events, |session| async move {
+        session.connected();
+        std::future::pending::<()>().await;
+    });
+    crate::event::wait_for(&events_rx, |event| matches!(event, Event::Connected(_))).await;
+    let started = Instant::now();
+    worker.close();
+    assert!(started.elapsed() < CLOSE_TIMEOUT * 2);
+    assert_eq!(worker.status(), Status::Closed);
+    let received = crate::event::wait_for(&events_rx, |event| {
+        matches!(event, Event::Disconnected(None))
+    })
+    .await;
+    assert_eq!(received.len(), 1);
+}
@@ -0,0 +1,131 @@
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+
+use crate::event::{Event, EventSender};
+use crate::record::{Direction, Record};
+use crate::transport::{queue_error, Outgoing, Session, Status, Transport, Worker};
+
+pub struct Udp {
+    worker: Worker,
+    write_tx: mpsc::Sender<Vec<u8>>,
+}
+impl Udp {
+    /// `remote` 为空时发送给最近一次收到数据报的对端
+    pub fn open(bind_host: &str, bind_port: u16, remote: &str, events: EventSender) -> Self {
+        let (write_tx, write_rx) = mpsc::channel(32);
+        let bind = format!("{}:{}", bind_host, bind_port);
+        let remote = match remote.trim() {
+            "" => None,
+            remote => Some(remote.to_string()),
+        };
+        let worker = Worker::spawn(events, |session| {
+            Self::read(bind, remote, write_rx, session)
+        });
+        Self { worker, write_tx }
+    }
+    pub async fn read(
+        bind: String,
+        remote: Option<String>,
+        mut write_rx: mpsc::Receiver<Vec<u8>>,
+        mut session: Session,
+    ) {
+        let events = session.events.clone();
+        let socket = match UdpSocket::bind(&bind).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                events.send(Event::Error(format!("绑定 {} 失败: {}", bind, e)));
+                return;
+            }
+        };
+        // 一个数据报就是一帧，按 UDP 最大载荷分配
+        let mut buf = vec![0; 65535];
+        let mut last_peer: Option<String> = None;
+        session.connected();
+        loop {
+            tokio::select! {
+                res = socket.recv_from(&mut buf) => {
+                    match res {
+                        Ok((n, addr)) => {
+                            let addr = addr.to_string();
+                            let record = Record::new(Direction::Rx, buf[..n].to_vec());
+                            events.send(Event::RxFrame(record.with_source(&addr)));
+                            last_peer = Some(addr);
+                        }
+                        Err(e) => events.send(Event::Error(format!("接收失败: {}", e))),
+                    }
+                }
+                Some(bytes) = write_rx.recv() => {
+                    match remote.as_deref().or(last_peer.as_deref()) {
+                        Some(addr) => match socket.send_to(&bytes, addr).await {
+                            Ok(_) => {
+                                let record = Record::new(Direction::Tx, bytes);
+                                events.send(Event::TxFrame(record.with_source(addr)));
+                            }
+                            Err(e) => events.send(Event::Error(format!("发送到 {} 失败: {}", addr, e))),
+                        },
+                        None => events.send(Event::Error("没有可发送的对端".to_string())),
+                    }
+                }
+                _ = session.shutdown_rx.recv() => {
+                    println!("Shutting down worker");
+                    break;
+                }
+            }
+        }
+    }
+}
+impl Transport for Udp {
+    fn write(&self, frame: Outgoing) -> Result<(), String> {
+        self.write_tx.try_send(frame.bytes).map_err(queue_error)
+    }
+    fn status(&self) -> Status {
+        self.worker.status()
+    }
+    fn close(&self) {
+        self.worker.close();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_udp_events() {
+    use crate::event::wait_for;
+
+    let port = std::net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let (events, events_rx) = EventSender::channel(None);
+    let udp = Udp::open("127.0.0.1", port, "", events);
+    wait_for(&events_rx, |event| matches!(event, Event::Connected(None))).await;
+    // 没有指定对端，也还没有收到过数据报
+    udp.write(Outgoing::new(vec![0x01])).unwrap();
+    let received = wait_for(&events_rx, |event| matches!(event, Event::Error(_))).await;
+    assert!(matches!(received.last(), Some(Event::Error(e)) if e == "没有可发送的对端"));
+
+    let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let peer_addr = peer.local_addr().unwrap().to_string();
+    peer.send_to(&[0x55, 0xAA], ("127.0.0.1", port))
+        .await
+        .unwrap();
+    let received = wait_for(&events_rx, |event| matches!(event, Event::RxFrame(_))).await;
+    let Some(Event::RxFrame(record)) = received.last() else {
+        unreachable!()
+    };
+    assert_eq!(record.bytes, vec![0x55, 0xAA]);
+    assert_eq!(record.source.as_deref(), Some(peer_addr.as_str()));
+
+    // 回复给最近一次收到数据报的对端
+    udp.write(Outgoing::new(vec![0x02])).unwrap();
+    let mut buf = [0; 16];
+    let (n, from) = peer.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], [0x02]);
+    assert_eq!(from.port(), port);
+    let received = wait_for(&events_rx, |event| matches!(event, Event::TxFrame(_))).await;
+    let Some(Event::TxFrame(record)) = received.last() else {
+        unreachable!()
+    };
+    assert_eq!(record.source.as_deref(), Some(peer_addr.as_str()));
+    udp.close();
+    assert_eq!(udp.status(), Status::Closed);
+}
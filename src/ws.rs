@@ -0,0 +1,151 @@
+use futures::{SinkExt, StreamExt};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::event::{Event, EventSender};
+use crate::record::{Direction, Record};
+use crate::transport::{queue_error, Outgoing, Session, Status, Transport, Worker};
+
+pub struct Ws {
+    worker: Worker,
+    write_tx: mpsc::Sender<Message>,
+}
+impl Ws {
+    pub fn open(url: &str, events: EventSender) -> Self {
+        let (write_tx, write_rx) = mpsc::channel(32);
+        let url = url.to_string();
+        let worker = Worker::spawn(events, |session| Self::read(url, write_rx, session));
+        Self { worker, write_tx }
+    }
+    pub async fn read(url: String, mut write_rx: mpsc::Receiver<Message>, mut session: Session) {
+        let events = session.events.clone();
+        let stream = tokio::select! {
+            res = tokio_tungstenite::connect_async(&url) => match res {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    events.send(Event::Error(format!("连接 {} 失败: {}", url, e)));
+                    return;
+                }
+            },
+            _ = session.shutdown_rx.recv() => return,
+        };
+        let (mut sink, mut stream) = stream.split();
+        session.connected();
+        loop {
+            tokio::select! {
+                res = stream.next() => {
+                    match res {
+                        Some(Ok(msg)) => {
+                            let opcode = opcode(&msg);
+                            let record = Record::new(Direction::Rx, msg.into_data().to_vec());
+                            events.send(Event::RxFrame(record.with_tag(opcode)));
+                        }
+                        Some(Err(e)) => {
+                            events.send(Event::Error(format!("读取失败: {}", e)));
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+                Some(msg) = write_rx.recv() => {
+                    let opcode = opcode(&msg);
+                    let bytes = msg.clone().into_data().to_vec();
+                    match sink.send(msg).await {
+                        Ok(()) => {
+                            let record = Record::new(Direction::Tx, bytes);
+                            events.send(Event::TxFrame(record.with_tag(opcode)));
+                        }
+                        Err(e) => events.send(Event::Error(format!("发送失败: {}", e))),
+                    }
+                }
+                _ = session.shutdown_rx.recv() => {
+                    let _ = sink.send(Message::Close(None)).await;
+                    println!("Shutting down worker");
+                    break;
+                }
+            }
+        }
+    }
+}
+impl Transport for Ws {
+    fn write(&self, frame: Outgoing) -> Result<(), String> {
+        let msg = match frame.text {
+            true => Message::text(String::from_utf8_lossy(&frame.bytes).into_owned()),
+            false => Message::binary(frame.bytes),
+        };
+        self.write_tx.try_send(msg).map_err(queue_error)
+    }
+    fn status(&self) -> Status {
+        self.worker.status()
+    }
+    fn close(&self) {
+        self.worker.close();
+    }
+}
+
+fn opcode(msg: &Message) -> &'static str {
+    match msg {
+        Message::Text(_) => "TEXT",
+        Message::Binary(_) => "BINARY",
+        Message::Ping(_) => "PING",
+        Message::Pong(_) => "PONG",
+        Message::Close(_) => "CLOSE",
+        Message::Frame(_) => "FRAME",
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_ws_events() {
+    use crate::event::wait_for;
+    use tokio::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (events, events_rx) = EventSender::channel(None);
+    let ws = Ws::open(&format!("ws://127.0.0.1:{}", port), events);
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut peer = tokio_tungstenite::accept_async(stream).await.unwrap();
+    wait_for(&events_rx, |event| matches!(event, Event::Connected(None))).await;
+
+    // 收到的帧带上帧类型
+    peer.send(Message::binary(vec![0x55, 0xAA])).await.unwrap();
+    peer.send(Message::text("OK")).await.unwrap();
+    let mut rx = Vec::new();
+    while rx.len() < 2 {
+        if let Some(Event::RxFrame(record)) =
+            wait_for(&events_rx, |event| matches!(event, Event::RxFrame(_)))
+                .await
+                .pop()
+        {
+            rx.push((record.tag.unwrap(), record.bytes));
+        }
+    }
+    assert_eq!(
+        rx,
+        vec![
+            ("BINARY".to_string(), vec![0x55, 0xAA]),
+            ("TEXT".to_string(), b"OK".to_vec())
+        ]
+    );
+
+    // 文本帧和二进制帧按 `text` 选择
+    let mut frame = Outgoing::new(b"hello".to_vec());
+    frame.text = true;
+    ws.write(frame).unwrap();
+    ws.write(Outgoing::new(vec![0x01])).unwrap();
+    assert_eq!(peer.next().await.unwrap().unwrap(), Message::text("hello"));
+    assert_eq!(
+        peer.next().await.unwrap().unwrap(),
+        Message::binary(vec![0x01])
+    );
+
+    // 对端关闭后任务结束
+    peer.close(None).await.unwrap();
+    drop(peer);
+    wait_for(&events_rx, |event| {
+        matches!(event, Event::Disconnected(None))
+    })
+    .await;
+    assert_eq!(ws.status(), Status::Closed);
+    ws.close();
+}